@@ -0,0 +1,41 @@
+//! Records the git commit, compiler and build time for `--version-info`.
+
+use std::env;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Runs `program` and returns its trimmed stdout, or `unknown` when it can't run or fails.
+fn output(program: &str, args: &[&str]) -> String {
+    Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn main() {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let built = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    println!(
+        "cargo:rustc-env=PWGEN_COMMIT={}",
+        output("git", &["rev-parse", "--short", "HEAD"])
+    );
+    println!(
+        "cargo:rustc-env=PWGEN_RUSTC={}",
+        output(&rustc, &["--version"])
+    );
+    println!("cargo:rustc-env=PWGEN_BUILD_TIME={}", built);
+    println!(
+        "cargo:rustc-env=PWGEN_TARGET={}",
+        env::var("TARGET").unwrap_or_default()
+    );
+    // Rebuild when HEAD moves, not on every source change
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
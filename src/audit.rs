@@ -0,0 +1,155 @@
+//! The `--audit-log` record of what was generated, which never includes the secret itself.
+
+use crate::error::AppError;
+use serde::Serialize;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Short flags that take a value: `-l`, `-n`, `-t` and `-w`.
+const SHORT_WITH_VALUES: &str = "lntw";
+
+/// One line of the audit log.
+#[derive(Serialize)]
+struct Entry<'a> {
+    timestamp: u64,
+    mode: &'a str,
+    length: usize,
+    pool_size: Option<usize>,
+    entropy_bits: f64,
+    flags: &'a [String],
+}
+
+/// An audit log opened for appending, one JSON line per generated secret.
+pub struct AuditLog {
+    file: File,
+    flags: Vec<String>,
+}
+
+impl AuditLog {
+    /// Opens `path` for appending, creating it readable by the owner only.
+    pub fn open(path: &Path) -> Result<Self, AppError> {
+        let mut options = OpenOptions::new();
+        options.append(true).create(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let file = options.open(path).map_err(|e| {
+            AppError::Config(format!("can't open audit log {}: {}", path.display(), e))
+        })?;
+        Ok(AuditLog {
+            file,
+            flags: flags(env::args().skip(1)),
+        })
+    }
+
+    /// Appends a line describing a secret of `length` characters; the secret itself never gets here.
+    pub fn record(
+        &mut self,
+        mode: &str,
+        length: usize,
+        pool_size: Option<usize>,
+        entropy_bits: f64,
+    ) -> io::Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let entry = Entry {
+            timestamp,
+            mode,
+            length,
+            pool_size,
+            entropy_bits,
+            flags: &self.flags,
+        };
+        let mut line = serde_json::to_vec(&entry).map_err(io::Error::from)?;
+        line.push(b'\n');
+        // One write per line, so concurrent runs appending to the same log don't interleave
+        self.file.write_all(&line)
+    }
+}
+
+/// The names of the flags in `args`, without their values.
+///
+/// Values are dropped because some, like `--check` or `--seed`, are secrets. In a short cluster like
+/// `-qlrandom`, everything after a flag that takes a value is that value, so it's cut off there.
+fn flags(args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut flags = Vec::new();
+    for arg in args {
+        if arg == "--" {
+            break;
+        }
+        if let Some(long) = arg.strip_prefix("--") {
+            let name = long.split('=').next().unwrap_or_default();
+            flags.push(format!("--{}", name));
+        } else if let Some(short) = arg.strip_prefix('-') {
+            for c in short.chars() {
+                flags.push(format!("-{}", c));
+                if SHORT_WITH_VALUES.contains(c) {
+                    break;
+                }
+            }
+        }
+    }
+    flags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, process};
+
+    fn args<'a>(args: &'a [&str]) -> impl Iterator<Item = String> + 'a {
+        args.iter().map(|arg| arg.to_string())
+    }
+
+    #[test]
+    fn flags_keep_names_and_drop_values() {
+        let flags = flags(args(&[
+            "--seed",
+            "abc",
+            "-l20",
+            "--check=hunter2",
+            "-qn5",
+            "-lrandom",
+            "-vvl20",
+            "passphrase",
+            "--",
+            "--not-a-flag",
+        ]));
+        assert_eq!(
+            flags,
+            ["--seed", "-l", "--check", "-q", "-n", "-l", "-v", "-v", "-l"]
+        );
+    }
+
+    #[test]
+    fn record_appends_one_line_of_metadata() {
+        let path = env::temp_dir().join(format!("pwgen-audit-{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let mut log = AuditLog::open(&path).unwrap();
+        log.flags = flags(args(&["--seed", "abc", "-l20"]));
+        log.record("password", 20, Some(90), 129.8).unwrap();
+        log.record("pin", 6, None, 19.9).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        let entry = &lines[0];
+        assert!(entry["timestamp"].as_u64().unwrap() > 0);
+        assert_eq!(entry["mode"], "password");
+        assert_eq!(entry["length"], 20);
+        assert_eq!(entry["pool_size"], 90);
+        assert_eq!(entry["entropy_bits"], 129.8);
+        assert_eq!(entry["flags"], serde_json::json!(["--seed", "-l"]));
+        assert_eq!(lines[1]["pool_size"], serde_json::Value::Null);
+        assert!(!contents.contains("abc"), "{}", contents);
+    }
+}
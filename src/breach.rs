@@ -0,0 +1,132 @@
+//! The `--check-breached` lookup in a local Have I Been Pwned password list.
+
+use crate::error::AppError;
+use sha1::{Digest, Sha1};
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// A file of `SHA1:COUNT` lines sorted by hash, like HIBP's ordered-by-hash download.
+///
+/// Lookups binary search the file on disk, so even the full list isn't read into memory.
+pub struct BreachList {
+    reader: BufReader<File>,
+    len: u64,
+}
+
+impl BreachList {
+    pub fn open(path: &Path) -> Result<Self, AppError> {
+        let opened = File::open(path).and_then(|file| Ok((file.metadata()?.len(), file)));
+        let (len, file) = opened
+            .map_err(|e| AppError::Config(format!("can't open {}: {}", path.display(), e)))?;
+        Ok(BreachList {
+            reader: BufReader::new(file),
+            len,
+        })
+    }
+
+    /// Whether the SHA-1 of `password` is in the list.
+    pub fn contains(&mut self, password: &str) -> io::Result<bool> {
+        let digest = Sha1::digest(password.as_bytes());
+        let target = Zeroizing::new(
+            digest
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect::<String>(),
+        );
+
+        // Any line holding the hash starts somewhere in lo..hi
+        let (mut lo, mut hi) = (0, self.len);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let (start, line) = match self.line_from(mid)? {
+                Some((start, line)) if start < hi => (start, line),
+                _ => {
+                    hi = mid;
+                    continue;
+                }
+            };
+            match hash(&line).as_slice().cmp(target.as_bytes()) {
+                Ordering::Equal => return Ok(true),
+                Ordering::Less => lo = start + line.len() as u64,
+                Ordering::Greater => hi = mid,
+            }
+        }
+        Ok(false)
+    }
+
+    /// The first line starting at or after `pos`, with its offset.
+    fn line_from(&mut self, pos: u64) -> io::Result<Option<(u64, Vec<u8>)>> {
+        let mut start = pos;
+        if pos > 0 {
+            // Skip the rest of the line `pos - 1` lies in, which ends right before `pos` at the latest
+            self.reader.seek(SeekFrom::Start(pos - 1))?;
+            start = pos - 1 + self.reader.read_until(b'\n', &mut Vec::new())? as u64;
+        } else {
+            self.reader.seek(SeekFrom::Start(0))?;
+        }
+        let mut line = Vec::new();
+        if self.reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some((start, line)))
+    }
+}
+
+/// The hash part of a `SHA1:COUNT` line, uppercased to match however the list was written.
+fn hash(line: &[u8]) -> Vec<u8> {
+    let hash = line.split(|byte| *byte == b':').next().unwrap_or_default();
+    hash.trim_ascii().to_ascii_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    /// A list file holding `passwords`, sorted by hash as HIBP's download is.
+    fn list_of(name: &str, passwords: &[String], line_end: &str) -> BreachList {
+        let mut lines: Vec<String> = passwords
+            .iter()
+            .map(|password| {
+                let hex: String = Sha1::digest(password.as_bytes())
+                    .iter()
+                    .map(|byte| format!("{:02X}", byte))
+                    .collect();
+                format!("{}:{}{}", hex, password.len(), line_end)
+            })
+            .collect();
+        lines.sort();
+        let path = env::temp_dir().join(format!("pwgen-breach-{}-{}", process::id(), name));
+        fs::write(&path, lines.concat()).unwrap();
+        let list = BreachList::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        list
+    }
+
+    #[test]
+    fn finds_every_listed_password_and_nothing_else() {
+        let listed: Vec<String> = (0..200).map(|i| format!("listed{}", i)).collect();
+        for (name, line_end) in [("lf", "\n"), ("crlf", "\r\n")] {
+            let mut list = list_of(name, &listed, line_end);
+            for password in &listed {
+                assert!(list.contains(password).unwrap(), "{}", password);
+            }
+            for i in 0..200 {
+                assert!(!list.contains(&format!("unlisted{}", i)).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn small_lists_are_searched_to_both_ends() {
+        let one = vec!["password".to_string()];
+        let mut list = list_of("one", &one, "\n");
+        assert!(list.contains("password").unwrap());
+        assert!(!list.contains("Password").unwrap());
+        let mut empty = list_of("empty", &[], "\n");
+        assert!(!empty.contains("password").unwrap());
+    }
+}
@@ -0,0 +1,551 @@
+//! Command-line arguments.
+
+use clap::builder::{NonEmptyStringValueParser, RangedU64ValueParser};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use pwgen::{ClassWeights, Separator};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+pub const LENGTH_RANGE: RangeInclusive<usize> = 10..=100;
+pub const TIMEOUT_RANGE: RangeInclusive<u64> = 0..=3600;
+pub const RANDOM_LENGTH_RANGE: RangeInclusive<usize> = 24..=48;
+
+/// Generate a random password
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    // Bare `pwgen` takes the same options as `pwgen password`
+    #[command(flatten)]
+    pub password: PasswordArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+
+    /// Log what pwgen is doing to stderr, never the password itself; repeat for more detail
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Rate an existing password instead of generating one; without a value it's read from a hidden prompt
+    #[arg(
+        long,
+        value_name = "PASSWORD",
+        num_args = 0..=1,
+        conflicts_with_all = ["ask", "pattern", "serve", "confirm", "fd", "output_file", "count", "timeout", "show", "reveal", "format", "no_clipboard", "check_breached", "audit_log"]
+    )]
+    pub check: Option<Option<String>>,
+
+    /// Print the characters a password would be drawn from, and how many bits each is worth, then exit
+    #[arg(
+        long,
+        conflicts_with_all = ["pattern", "check", "serve", "confirm", "fd", "output_file", "count", "timeout", "show", "reveal", "format", "no_clipboard"]
+    )]
+    pub show_pool: bool,
+
+    /// Generate N passwords and print how evenly their characters cover the pool, then exit
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..=1_000_000),
+        conflicts_with_all = ["ask", "pattern", "check", "show_pool", "serve", "confirm", "fd", "output_file", "count", "timeout", "show", "reveal", "format", "no_clipboard", "check_breached", "audit_log"]
+    )]
+    pub stats: Option<usize>,
+
+    /// Answer `[LENGTH] [symbols|no-symbols]` requests on stdin with one password per line until EOF
+    #[arg(
+        long,
+        conflicts_with_all = ["ask", "pattern", "confirm", "fd", "output_file", "copy_only", "count", "timeout", "quiet", "show", "reveal", "format", "no_clipboard", "check_breached", "audit_log"]
+    )]
+    pub serve: bool,
+
+    /// Copy stdin to stdout, replacing every {{pw:N}} with a fresh password of N characters
+    #[arg(
+        long,
+        conflicts_with_all = ["ask", "pattern", "check", "show_pool", "stats", "serve", "confirm", "fd", "output_file", "copy_only", "count", "timeout", "quiet", "show", "reveal", "format", "no_clipboard", "check_breached", "audit_log"]
+    )]
+    pub stdin_template: bool,
+
+    /// Print version, commit, compiler, features and clipboard backend as key=value lines, then exit
+    #[arg(long, exclusive = true)]
+    pub version_info: bool,
+
+    /// Use the defaults saved under [profiles.NAME] in the config file; flags still override them
+    #[arg(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Where random numbers come from: os is secure, seeded is reproducible and INSECURE, for testing only
+    #[arg(long, global = true, value_enum, default_value_t = RngSource::Os)]
+    pub rng_source: RngSource,
+
+    /// Seed for --rng-source seeded: up to 64 hex digits, padded with zeros
+    #[arg(
+        long,
+        global = true,
+        value_name = "HEX",
+        value_parser = parse_seed,
+        required_if_eq("rng_source", "seeded")
+    )]
+    pub seed: Option<[u8; 32]>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Generate a random password (the default)
+    Password(PasswordArgs),
+    /// Generate a diceware-style passphrase from the EFF large wordlist
+    Passphrase(PassphraseArgs),
+    /// Generate a pronounceable password of alternating consonants and vowels
+    Pronounceable(PronounceableArgs),
+    /// Generate two capitalized words around a digit and a symbol, like Horse7!Battery
+    Hybrid,
+    /// Generate a numeric PIN
+    Pin(PinArgs),
+    /// Derive a site's password from a master password, so nothing needs storing
+    Derive(DeriveArgs),
+    /// Print a shell completion script to stdout
+    #[command(hide = true)]
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Print the man page, in roff, to stdout
+    #[command(hide = true)]
+    Manpage,
+}
+
+/// Options for derived passwords.
+#[derive(Args, Debug)]
+pub struct DeriveArgs {
+    /// Site or account name the password is for; the same name always gives the same password
+    #[arg(long, value_name = "NAME", value_parser = NonEmptyStringValueParser::new(), conflicts_with = "seed")]
+    pub site: String,
+
+    /// Bump this to rotate the site's password without changing the master password
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = RangedU64ValueParser::<u32>::new().range(1..=u64::from(u32::MAX)))]
+    pub counter: u32,
+
+    #[command(flatten)]
+    pub password: PasswordArgs,
+}
+
+/// Options for random passwords.
+#[derive(Args, Debug)]
+pub struct PasswordArgs {
+    /// Ask for password length and symbol preference
+    #[arg(long)]
+    pub ask: bool,
+
+    /// Password length (minimum 10, maximum 100), or random for one between 24 and 48
+    #[arg(short, long, value_parser = parse_password_length, conflicts_with = "ask")]
+    pub length: Option<Length>,
+
+    /// Pick the shortest length with at least N bits of entropy for the chosen characters
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = RangedU64ValueParser::<u32>::new().range(1..=1000),
+        conflicts_with_all = ["ask", "length"]
+    )]
+    pub bits: Option<u32>,
+
+    /// Include symbols in the password (default)
+    #[arg(long, conflicts_with = "no_symbols")]
+    pub symbols: bool,
+
+    /// Exclude symbols from the password
+    #[arg(long)]
+    pub no_symbols: bool,
+
+    /// Guarantee at least one lowercase letter, uppercase letter, digit and (if enabled) symbol
+    #[arg(long)]
+    pub require_classes: bool,
+
+    /// Minimum number of digits in the password
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub min_digits: usize,
+
+    /// Minimum number of symbols in the password
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        conflicts_with = "no_symbols"
+    )]
+    pub min_symbols: usize,
+
+    /// Draw only from these characters instead of the built-in letters, digits and symbols
+    #[arg(
+        long,
+        value_name = "STRING",
+        conflicts_with_all = ["ask", "symbols", "no_symbols", "require_classes", "min_digits", "min_symbols"]
+    )]
+    pub charset: Option<String>,
+
+    /// Remove these characters from the pool
+    #[arg(long, value_name = "STRING", default_value = "")]
+    pub exclude: String,
+
+    /// Reject passwords with three or more identical characters in a row
+    #[arg(long)]
+    pub no_repeats: bool,
+
+    /// Reject passwords with three or more consecutive letters or digits, like abc or 321
+    #[arg(long)]
+    pub no_sequences: bool,
+
+    /// Use only uppercase letters
+    #[arg(long, conflicts_with = "lowercase_only")]
+    pub uppercase_only: bool,
+
+    /// Use only lowercase letters
+    #[arg(long)]
+    pub lowercase_only: bool,
+
+    /// Start and end the password with a letter or digit; symbols may still appear in between
+    #[arg(long)]
+    pub no_start_end_symbol: bool,
+
+    /// Leave out easily confused characters (l, 1, I, O, 0)
+    #[arg(long)]
+    pub no_ambiguous: bool,
+
+    /// Only use letters, digits and -._~, which are safe in URLs and shell single quotes
+    #[arg(long)]
+    pub url_safe: bool,
+
+    /// Regenerate if the password contains this word, ignoring case and look-alikes; repeatable
+    #[arg(long, value_name = "WORD", value_parser = NonEmptyStringValueParser::new())]
+    pub avoid_similar_to: Vec<String>,
+
+    /// Draw character classes by weight, like lower=3,upper=3,digit=2,symbol=1, instead of uniformly
+    #[arg(long, value_name = "WEIGHTS", value_parser = parse_weights, conflicts_with_all = ["bits", "charset"])]
+    pub weight: Option<ClassWeights>,
+
+    /// Regenerate until the password matches this regex; lookahead like (?=.*\d) is supported
+    #[arg(long, value_name = "PATTERN")]
+    pub require_regex: Option<String>,
+
+    /// Regenerate if the password contains a very common word, like password or qwerty
+    #[arg(long)]
+    pub avoid_common_words: bool,
+
+    /// Follow a template: A uppercase, a lowercase, 9 digit, ! symbol; \ escapes, other letters and digits are errors, the rest is literal
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        conflicts_with_all = ["ask", "length", "bits", "symbols", "no_symbols", "require_classes", "min_digits", "min_symbols", "charset", "exclude", "no_repeats", "no_sequences", "uppercase_only", "lowercase_only", "no_start_end_symbol", "no_ambiguous", "url_safe", "avoid_similar_to", "weight", "require_regex", "avoid_common_words"]
+    )]
+    pub pattern: Option<String>,
+}
+
+/// Options for passphrases.
+#[derive(Args, Debug)]
+pub struct PassphraseArgs {
+    /// Number of words
+    #[arg(short, long, default_value_t = 6, value_parser = RangedU64ValueParser::<usize>::new().range(3..=20))]
+    pub words: usize,
+
+    /// Character placed between words, or `digit` or `symbol` for a random one in each gap
+    #[arg(long, value_name = "CHAR", default_value = "-", value_parser = parse_separator)]
+    pub separator: Separator,
+
+    /// Capitalize the first letter of every word; adds no entropy
+    #[arg(long, conflicts_with = "title_random")]
+    pub capitalize: bool,
+
+    /// Capitalize the first letter of one random word; adds only a couple of bits
+    #[arg(long)]
+    pub title_random: bool,
+}
+
+/// Options for pronounceable passwords.
+#[derive(Args, Debug)]
+pub struct PronounceableArgs {
+    /// Password length (minimum 10, maximum 100)
+    #[arg(short, long, value_parser = parse_length)]
+    pub length: Option<usize>,
+}
+
+/// Options for PINs.
+#[derive(Args, Debug)]
+pub struct PinArgs {
+    /// Number of digits
+    #[arg(short, long, default_value_t = 6, value_parser = RangedU64ValueParser::<usize>::new().range(4..=20))]
+    pub length: usize,
+}
+
+/// Options for how any generated secret is delivered, accepted before or after the subcommand.
+#[derive(Args, Debug)]
+pub struct OutputArgs {
+    /// Number of passwords to generate; more than one prints them all and skips the clipboard
+    #[arg(short = 'n', long, global = true, default_value_t = 1, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub count: usize,
+
+    /// Seconds before the clipboard is cleared (default 15); 0 copies without clearing
+    #[arg(short, long, global = true, value_name = "SECONDS", value_parser = RangedU64ValueParser::<u64>::new().range(TIMEOUT_RANGE))]
+    pub timeout: Option<u64>,
+
+    /// Copy the password and exit at once, printing nothing and never clearing the clipboard
+    #[arg(
+        long,
+        global = true,
+        conflicts_with_all = ["count", "timeout", "quiet", "show", "reveal", "format", "no_clipboard", "no_auto_clear", "fd", "output_file"]
+    )]
+    pub copy_only: bool,
+
+    /// Copy without a countdown and never clear the clipboard; prints a warning to clear it yourself
+    #[arg(long, global = true, conflicts_with = "timeout")]
+    pub no_auto_clear: bool,
+
+    /// Wait for Enter before generating, so nothing appears on a shared screen until you're ready
+    #[arg(long, global = true)]
+    pub confirm: bool,
+
+    /// After showing the password, offer to replace it with a fresh one before the countdown starts
+    #[arg(
+        long,
+        global = true,
+        conflicts_with_all = ["copy_only", "fd", "output_file", "count", "format", "no_clipboard"]
+    )]
+    pub interactive: bool,
+
+    /// Show the masked password first and only copy it once Enter is pressed, then start the countdown
+    #[arg(
+        long,
+        global = true,
+        conflicts_with_all = ["interactive", "copy_only", "fd", "output_file", "count", "format", "no_clipboard"]
+    )]
+    pub copy_on_accept: bool,
+
+    /// Append N random digits to the password or passphrase
+    #[arg(long, global = true, value_name = "N", value_parser = RangedU64ValueParser::<usize>::new().range(1..=20))]
+    pub append_digits: Option<usize>,
+
+    /// Insert a separator every N characters for readability
+    #[arg(long, global = true, value_name = "N", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub separator_every: Option<usize>,
+
+    /// Character inserted by --separator-every
+    #[arg(
+        long,
+        global = true,
+        value_name = "CHAR",
+        default_value_t = '-',
+        requires = "separator_every"
+    )]
+    pub group_char: char,
+
+    /// Suppress the countdown, final message and screen clearing; the clipboard is still cleared
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Keep the masked password and timer on screen afterwards; the clipboard is still cleared
+    #[arg(long, global = true)]
+    pub no_clear_screen: bool,
+
+    /// Show the countdown as a shrinking bar; plain text is kept when stdout isn't a terminal
+    #[arg(long, global = true)]
+    pub progress: bool,
+
+    /// Display the full password instead of masking it; the clipboard and timer work as usual
+    #[arg(long, global = true)]
+    pub show: bool,
+
+    /// Characters to reveal at each end of the masked password (default 5 leading, 3 trailing)
+    #[arg(long, global = true, value_name = "N", conflicts_with = "show")]
+    pub reveal: Option<usize>,
+
+    /// Output format; json prints each password and its metadata, json-batch one array of them, both skip the clipboard
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// Write the password to this inherited file descriptor instead of the terminal or clipboard (Unix only)
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        conflicts_with_all = ["timeout", "show", "reveal", "format", "no_clipboard", "no_auto_clear", "confirm"]
+    )]
+    pub fd: Option<u32>,
+
+    /// Write the passwords, one per line, to a new file readable only by you, synced to disk before exiting
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        conflicts_with_all = ["timeout", "show", "reveal", "format", "no_clipboard", "no_auto_clear", "fd"]
+    )]
+    pub output_file: Option<PathBuf>,
+
+    /// Type the password into the focused window instead of copying it, after --autotype-delay seconds
+    #[cfg(feature = "autotype")]
+    #[arg(
+        long,
+        global = true,
+        conflicts_with_all = ["timeout", "show", "reveal", "format", "no_clipboard", "no_auto_clear", "copy_only", "fd", "output_file"]
+    )]
+    pub autotype: bool,
+
+    /// Seconds to wait before typing, to focus the target field
+    #[cfg(feature = "autotype")]
+    #[arg(long, global = true, value_name = "SECONDS", default_value_t = 3, requires = "autotype", value_parser = RangedU64ValueParser::<u64>::new().range(0..=60))]
+    pub autotype_delay: u64,
+
+    /// Print the full password to stdout instead of copying it to the clipboard
+    #[arg(long, global = true)]
+    pub no_clipboard: bool,
+
+    /// Regenerate while the password is in FILE, a hash-sorted Have I Been Pwned list; nothing is sent anywhere
+    #[arg(long, global = true, value_name = "FILE")]
+    pub check_breached: Option<PathBuf>,
+
+    /// Append a JSON line of metadata per generated secret to PATH, never the secret itself
+    #[arg(long, global = true, value_name = "PATH")]
+    pub audit_log: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RngSource {
+    /// The operating system's CSPRNG
+    Os,
+    /// A generator seeded with --seed, so the same seed gives the same output; never for real passwords
+    Seeded,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    JsonBatch,
+}
+
+pub fn parse_length(s: &str) -> Result<usize, String> {
+    match s.trim().parse() {
+        Ok(num) if LENGTH_RANGE.contains(&num) => Ok(num),
+        _ => Err(format!(
+            "Please enter a number between {} and {}.",
+            LENGTH_RANGE.start(),
+            LENGTH_RANGE.end()
+        )),
+    }
+}
+
+/// A `--length` value: a number, or `random` to pick one from `RANDOM_LENGTH_RANGE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Length {
+    Fixed(usize),
+    Random,
+}
+
+/// Parses a password length, or the `random` keyword.
+pub fn parse_password_length(s: &str) -> Result<Length, String> {
+    if s.trim() == "random" {
+        return Ok(Length::Random);
+    }
+    parse_length(s).map(Length::Fixed).map_err(|_| {
+        format!(
+            "Please enter a number between {} and {}, or random.",
+            LENGTH_RANGE.start(),
+            LENGTH_RANGE.end()
+        )
+    })
+}
+
+/// Parses a passphrase separator: `digit`, `symbol` or a single character.
+pub fn parse_separator(s: &str) -> Result<Separator, String> {
+    let mut chars = s.chars();
+    match (s, chars.next(), chars.next()) {
+        ("digit", ..) => Ok(Separator::RandomDigit),
+        ("symbol", ..) => Ok(Separator::RandomSymbol),
+        (_, Some(c), None) => Ok(Separator::Fixed(c)),
+        _ => Err("Please enter a single character, digit or symbol.".to_string()),
+    }
+}
+
+/// Parses class weights like `lower=3,upper=3,digit=2,symbol=1`; classes left out keep a weight of 1.
+pub fn parse_weights(s: &str) -> Result<ClassWeights, String> {
+    let mut weights = ClassWeights::default();
+    for part in s.split(',') {
+        let (class, weight) = part
+            .split_once('=')
+            .ok_or_else(|| format!("Please write '{}' as CLASS=WEIGHT.", part))?;
+        let weight: u32 = weight
+            .trim()
+            .parse()
+            .map_err(|_| format!("Please enter a whole number of 0 or more for {}.", class))?;
+        match class.trim() {
+            "lower" => weights.lowercase = weight,
+            "upper" => weights.uppercase = weight,
+            "digit" => weights.digit = weight,
+            "symbol" => weights.symbol = weight,
+            _ => {
+                return Err(format!(
+                    "Unknown class '{}', use lower, upper, digit or symbol.",
+                    class
+                ))
+            }
+        }
+    }
+    Ok(weights)
+}
+
+/// Parses up to 64 hex digits into a 32-byte seed, padding shorter seeds with trailing zeros.
+pub fn parse_seed(s: &str) -> Result<[u8; 32], String> {
+    let s = s.trim();
+    if s.is_empty() || s.len() > 64 || !s.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("Please enter between 1 and 64 hex digits.".to_string());
+    }
+    let mut seed = [0; 32];
+    for (i, c) in s.chars().enumerate() {
+        let digit = c.to_digit(16).unwrap() as u8;
+        seed[i / 2] |= if i % 2 == 0 { digit << 4 } else { digit };
+    }
+    Ok(seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn length_is_a_number_or_random() {
+        assert_eq!(parse_password_length("20"), Ok(Length::Fixed(20)));
+        assert_eq!(parse_password_length(" 100 "), Ok(Length::Fixed(100)));
+        assert_eq!(parse_password_length("random"), Ok(Length::Random));
+        assert!(parse_password_length("9").is_err());
+        assert!(parse_password_length("101").is_err());
+        assert!(parse_password_length("Random").is_err());
+    }
+
+    #[test]
+    fn length_parses_from_the_command_line() {
+        let cli = Cli::parse_from(["pwgen", "--length", "random"]);
+        assert_eq!(cli.password.length, Some(Length::Random));
+        let cli = Cli::parse_from(["pwgen", "password", "-l", "30"]);
+        let Some(Command::Password(password)) = cli.command else {
+            panic!("expected the password subcommand");
+        };
+        assert_eq!(password.length, Some(Length::Fixed(30)));
+    }
+
+    #[test]
+    fn seed_is_hex_padded_with_zeros() {
+        let mut expected = [0; 32];
+        expected[0] = 0xab;
+        expected[1] = 0xc0;
+        assert_eq!(parse_seed("abc"), Ok(expected));
+        assert_eq!(parse_seed(&"f".repeat(64)), Ok([0xff; 32]));
+        assert!(parse_seed("").is_err());
+        assert!(parse_seed("xyz").is_err());
+        assert!(parse_seed(&"0".repeat(65)).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! The clipboard a password is copied to, and checked and cleared afterwards.
+
+use crate::error::AppError;
+use arboard::Clipboard;
+#[cfg(target_os = "macos")]
+use arboard::SetExtApple;
+#[cfg(all(unix, not(target_os = "macos")))]
+use arboard::SetExtLinux;
+#[cfg(windows)]
+use arboard::SetExtWindows;
+use log::{debug, info};
+use std::env;
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::process;
+use std::time::Instant;
+use zeroize::Zeroizing;
+
+/// What pwgen needs from a clipboard, so the flows around it can run without a display.
+pub trait SecretClipboard {
+    /// Puts `password` on the clipboard, marked as sensitive where the platform allows.
+    fn copy(&mut self, password: &str) -> Result<(), arboard::Error>;
+
+    /// The text on the clipboard right now.
+    fn text(&mut self) -> Result<Zeroizing<String>, arboard::Error>;
+
+    /// Empties the clipboard.
+    fn clear(&mut self) -> Result<(), arboard::Error>;
+}
+
+impl SecretClipboard for Clipboard {
+    /// The hint is `x-kde-passwordManagerHint` on Linux, the nspasteboard.org concealed type on macOS
+    /// and the history, cloud and monitoring exclusion formats on Windows. Managers that ignore it
+    /// will still record the password.
+    fn copy(&mut self, password: &str) -> Result<(), arboard::Error> {
+        let started = Instant::now();
+        let set = self.set();
+        #[cfg(windows)]
+        let set = set.exclude_from_monitoring().exclude_from_cloud();
+        let result = set.exclude_from_history().text(password);
+        debug!("clipboard set in {:?}: {:?}", started.elapsed(), result);
+        result
+    }
+
+    fn text(&mut self) -> Result<Zeroizing<String>, arboard::Error> {
+        self.get_text().map(Zeroizing::new)
+    }
+
+    fn clear(&mut self) -> Result<(), arboard::Error> {
+        clear_wayland_clipboard();
+        Clipboard::clear(self)
+    }
+}
+
+/// Which clipboard arboard is most likely talking to, for the logs.
+pub fn clipboard_backend() -> &'static str {
+    if cfg!(windows) {
+        "Windows clipboard"
+    } else if cfg!(target_os = "macos") {
+        "macOS pasteboard"
+    } else if env::var_os("DISPLAY").is_some_and(|display| !display.is_empty()) {
+        "X11 (or XWayland) selection"
+    } else {
+        "X11 selection, but DISPLAY is unset"
+    }
+}
+
+/// Suggests how to get the clipboard working, based on why it failed and what display is around.
+pub fn clipboard_hint(error: &arboard::Error) -> String {
+    if matches!(error, arboard::Error::ClipboardOccupied) {
+        return "another application is holding the clipboard; try again in a moment".to_string();
+    }
+    if cfg!(windows) {
+        "the Windows clipboard couldn't be opened; try again or use --no-clipboard".to_string()
+    } else if cfg!(target_os = "macos") {
+        "the pasteboard is only available in a logged-in GUI session; use --no-clipboard otherwise"
+            .to_string()
+    } else {
+        let set = |name: &str| env::var_os(name).is_some_and(|value| !value.is_empty());
+        match env::var("DISPLAY") {
+            Ok(display) if !display.is_empty() => format!(
+                "couldn't reach the X server at DISPLAY={}; check that it's running and that XAUTHORITY lets you connect",
+                display
+            ),
+            _ if set("SSH_CONNECTION") || set("SSH_TTY") => {
+                "no display found over SSH; reconnect with `ssh -X` or use --no-clipboard".to_string()
+            }
+            _ if set("WAYLAND_DISPLAY") => {
+                "only Wayland was found; start XWayland and set DISPLAY, or use --no-clipboard"
+                    .to_string()
+            }
+            _ => "no display found; try --no-clipboard or set DISPLAY".to_string(),
+        }
+    }
+}
+
+/// Also clears the native Wayland clipboard with `wl-copy --clear`, if it's installed.
+///
+/// arboard reaches Wayland through XWayland, and clearing the X selection doesn't always reach the
+/// compositor's own copy, which outlives pwgen.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn clear_wayland_clipboard() {
+    if env::var_os("WAYLAND_DISPLAY").is_none_or(|display| display.is_empty()) {
+        return;
+    }
+    let status = process::Command::new("wl-copy")
+        .arg("--clear")
+        .stdin(process::Stdio::null())
+        .status();
+    match status {
+        Ok(status) if status.success() => info!("cleared the Wayland clipboard with wl-copy"),
+        Ok(status) => debug!("wl-copy --clear failed: {}", status),
+        Err(e) => debug!("wl-copy isn't available: {}", e),
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn clear_wayland_clipboard() {}
+
+/// Clears the clipboard and reads it back, retrying once if the password is still there.
+pub fn clear_clipboard(
+    clipboard: &mut impl SecretClipboard,
+    password: &str,
+) -> Result<(), AppError> {
+    let mut result = Ok(());
+    for attempt in 1..=2 {
+        result = match clipboard.clear() {
+            Ok(()) => {
+                // Another app may have put it back, or the clear silently did nothing
+                let text = clipboard.text();
+                if text.is_ok_and(|text| text.as_str() == password) {
+                    debug!(
+                        "clear attempt {}: the password is still on the clipboard",
+                        attempt
+                    );
+                    Err(AppError::ClipboardNotCleared)
+                } else {
+                    info!("clipboard cleared on attempt {}", attempt);
+                    return Ok(());
+                }
+            }
+            Err(e) => {
+                debug!("clear attempt {} failed: {}", attempt, e);
+                Err(AppError::Clipboard(e))
+            }
+        };
+    }
+    result
+}
+
+/// Whether the clipboard still holds `password`, so clearing it won't destroy something copied since.
+///
+/// A failed read is taken as still ours, except when the clipboard no longer holds text at all.
+pub fn still_on_clipboard(clipboard: &mut impl SecretClipboard, password: &str) -> bool {
+    match clipboard.text() {
+        Ok(text) => text.as_str() == password,
+        Err(arboard::Error::ContentNotAvailable) => false,
+        Err(e) => {
+            debug!("couldn't read the clipboard back: {}", e);
+            true
+        }
+    }
+}
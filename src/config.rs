@@ -0,0 +1,180 @@
+//! Defaults read from `~/.config/pwgen/config.toml`, its profiles and `PWGEN_*` environment variables.
+
+use crate::cli::{LENGTH_RANGE, TIMEOUT_RANGE};
+use log::debug;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::{env, fs, io};
+
+/// Defaults for command-line options, which flags override.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub length: Option<usize>,
+    pub symbols: Option<bool>,
+    pub timeout: Option<u64>,
+    pub charset: Option<String>,
+    pub no_ambiguous: Option<bool>,
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+}
+
+/// A named bundle of defaults under `[profiles.NAME]`, selected with `--profile NAME`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Profile {
+    length: Option<usize>,
+    symbols: Option<bool>,
+    timeout: Option<u64>,
+    charset: Option<String>,
+    no_ambiguous: Option<bool>,
+}
+
+/// Location of the config file, following the XDG base directory convention.
+fn path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("pwgen").join("config.toml"))
+}
+
+/// Reads the config file, lets `PWGEN_LENGTH`, `PWGEN_SYMBOLS` and `PWGEN_TIMEOUT` override it, then
+/// applies `profile` on top, since choosing one on the command line is the more specific request.
+pub fn load(profile: Option<&str>) -> Result<Config, String> {
+    let mut config = read_file()?;
+    let length = from_env(
+        "PWGEN_LENGTH",
+        |value| {
+            value
+                .parse()
+                .ok()
+                .filter(|length| LENGTH_RANGE.contains(length))
+        },
+        &format!(
+            "a length between {} and {}",
+            LENGTH_RANGE.start(),
+            LENGTH_RANGE.end()
+        ),
+    )?;
+    let symbols = from_env(
+        "PWGEN_SYMBOLS",
+        |value| match value.to_lowercase().as_str() {
+            "true" | "yes" | "1" => Some(true),
+            "false" | "no" | "0" => Some(false),
+            _ => None,
+        },
+        "true or false",
+    )?;
+    let timeout = from_env(
+        "PWGEN_TIMEOUT",
+        |value| {
+            value
+                .parse()
+                .ok()
+                .filter(|timeout| TIMEOUT_RANGE.contains(timeout))
+        },
+        &format!(
+            "a timeout between {} and {}",
+            TIMEOUT_RANGE.start(),
+            TIMEOUT_RANGE.end()
+        ),
+    )?;
+    config.length = length.or(config.length);
+    config.symbols = symbols.or(config.symbols);
+    config.timeout = timeout.or(config.timeout);
+
+    if let Some(name) = profile {
+        let Some(profile) = config.profiles.remove(name) else {
+            let known: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+            return Err(if known.is_empty() {
+                format!("no profile '{}': the config file defines none", name)
+            } else {
+                format!(
+                    "no profile '{}', expected one of: {}",
+                    name,
+                    known.join(", ")
+                )
+            });
+        };
+        debug!("using profile {}", name);
+        config.length = profile.length.or(config.length);
+        config.symbols = profile.symbols.or(config.symbols);
+        config.timeout = profile.timeout.or(config.timeout);
+        config.charset = profile.charset.or(config.charset);
+        config.no_ambiguous = profile.no_ambiguous.or(config.no_ambiguous);
+    }
+    Ok(config)
+}
+
+/// Reads and parses the environment variable `name`, if it's set.
+fn from_env<T>(
+    name: &str,
+    parse: impl Fn(&str) -> Option<T>,
+    expected: &str,
+) -> Result<Option<T>, String> {
+    let Some(value) = env::var_os(name) else {
+        return Ok(None);
+    };
+    let value = value.to_string_lossy();
+    match parse(value.trim()) {
+        Some(parsed) => {
+            debug!("using {} from the environment", name);
+            Ok(Some(parsed))
+        }
+        None => Err(format!(
+            "invalid {} '{}': expected {}",
+            name, value, expected
+        )),
+    }
+}
+
+/// Reads the config file, or returns empty defaults if there isn't one.
+fn read_file() -> Result<Config, String> {
+    let Some(path) = path() else {
+        return Ok(Config::default());
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            debug!("no config file at {}", path.display());
+            return Ok(Config::default());
+        }
+        Err(e) => return Err(format!("can't read {}: {}", path.display(), e)),
+    };
+
+    debug!("read config from {}", path.display());
+    let config: Config =
+        toml::from_str(&contents).map_err(|e| format!("invalid {}: {}", path.display(), e))?;
+    check_ranges(config.length, config.timeout, &path.display().to_string())?;
+    for (name, profile) in &config.profiles {
+        check_ranges(
+            profile.length,
+            profile.timeout,
+            &format!("profile '{}' in {}", name, path.display()),
+        )?;
+    }
+    Ok(config)
+}
+
+/// Rejects a configured length or timeout outside what the flags accept; `place` names where it came from.
+fn check_ranges(length: Option<usize>, timeout: Option<u64>, place: &str) -> Result<(), String> {
+    if length.is_some_and(|length| !LENGTH_RANGE.contains(&length)) {
+        return Err(format!(
+            "invalid {}: length must be between {} and {}",
+            place,
+            LENGTH_RANGE.start(),
+            LENGTH_RANGE.end()
+        ));
+    }
+    if timeout.is_some_and(|timeout| !TIMEOUT_RANGE.contains(&timeout)) {
+        return Err(format!(
+            "invalid {}: timeout must be between {} and {}",
+            place,
+            TIMEOUT_RANGE.start(),
+            TIMEOUT_RANGE.end()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,65 @@
+//! Errors that end the program, and the exit codes they map to.
+
+use std::{fmt, io};
+
+/// A failure that ends the program.
+///
+/// Each variant maps to its own exit code, so scripts can tell them apart:
+///
+/// * `0`: success
+/// * `1`: reading input or writing output failed
+/// * `2`: invalid arguments, configuration or password options
+/// * `3`: the clipboard was unavailable or couldn't be cleared
+#[derive(Debug)]
+pub enum AppError {
+    Io(io::Error),
+    Config(String),
+    Password(pwgen::Error),
+    Clipboard(arboard::Error),
+    ClipboardNotCleared,
+}
+
+impl AppError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::Io(_) => 1,
+            AppError::Config(_) | AppError::Password(_) => 2,
+            AppError::Clipboard(_) | AppError::ClipboardNotCleared => 3,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Io(e) => write!(f, "{}", e),
+            AppError::Config(e) => write!(f, "{}", e),
+            AppError::Password(e) => write!(f, "{}", e),
+            AppError::Clipboard(e) => write!(f, "clipboard error: {}", e),
+            AppError::ClipboardNotCleared => write!(
+                f,
+                "the password is still on the clipboard, clear it manually"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<io::Error> for AppError {
+    fn from(e: io::Error) -> Self {
+        AppError::Io(e)
+    }
+}
+
+impl From<pwgen::Error> for AppError {
+    fn from(e: pwgen::Error) -> Self {
+        AppError::Password(e)
+    }
+}
+
+impl From<arboard::Error> for AppError {
+    fn from(e: arboard::Error) -> Self {
+        AppError::Clipboard(e)
+    }
+}
@@ -0,0 +1,1292 @@
+//! Password generation behind the `pwgen` command line tool.
+
+use fancy_regex::Regex;
+use rand::distributions::{Distribution, Uniform, WeightedIndex};
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use rand::{CryptoRng, Rng};
+use std::fmt;
+use zeroize::{Zeroize, Zeroizing};
+
+mod derive;
+mod mask;
+mod passphrase;
+mod pattern;
+mod pronounceable;
+mod strength;
+
+pub use derive::{derive_key, derive_password};
+pub use mask::{mask_password, Reveal};
+pub use passphrase::{
+    generate_hybrid, generate_hybrid_with_rng, generate_passphrase, generate_passphrase_with_rng,
+    hybrid_entropy, passphrase_entropy, wordlist, Capitalization, Separator,
+};
+pub use pattern::{generate_from_pattern, generate_from_pattern_with_rng, pattern_entropy};
+pub use pronounceable::{
+    generate_pronounceable, generate_pronounceable_with_rng, pronounceable_entropy,
+};
+pub use strength::{classify_strength, password_entropy, Strength};
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!@#$%^&*()-=_+[]{}|;:',.<>?/";
+const AMBIGUOUS: &str = "l1IO0";
+// Unreserved in URLs (RFC 3986) and inert inside shell single quotes
+const URL_SAFE_SYMBOLS: &str = "-._~";
+// Words too common to appear in a password, checked by `avoid_common_words`
+const COMMON_WORDS: &[&str] = &[
+    "password", "passwd", "admin", "login", "qwerty", "letmein", "welcome", "dragon", "monkey",
+    "master", "secret", "shadow", "sunshine", "princess", "football", "baseball", "iloveyou",
+    "trustno1", "abc123", "123456",
+];
+// Retry cap for rejected passwords, so a tiny charset can't loop forever
+const MAX_ATTEMPTS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Lowercase,
+    Uppercase,
+    Digit,
+    Symbol,
+}
+
+impl CharClass {
+    fn name(self) -> &'static str {
+        match self {
+            CharClass::Lowercase => "lowercase letter",
+            CharClass::Uppercase => "uppercase letter",
+            CharClass::Digit => "digit",
+            CharClass::Symbol => "symbol",
+        }
+    }
+
+    fn chars(self) -> &'static str {
+        match self {
+            CharClass::Lowercase => LOWERCASE,
+            CharClass::Uppercase => UPPERCASE,
+            CharClass::Digit => DIGITS,
+            CharClass::Symbol => SYMBOLS,
+        }
+    }
+}
+
+/// Relative odds of drawing each character class, e.g. digits twice as often as symbols.
+///
+/// A class is drawn by its weight, then a character uniformly within it. A weight of 0 leaves a class
+/// out except for any minimum it must still meet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClassWeights {
+    pub lowercase: u32,
+    pub uppercase: u32,
+    pub digit: u32,
+    pub symbol: u32,
+}
+
+impl Default for ClassWeights {
+    fn default() -> Self {
+        ClassWeights {
+            lowercase: 1,
+            uppercase: 1,
+            digit: 1,
+            symbol: 1,
+        }
+    }
+}
+
+impl ClassWeights {
+    fn of(self, class: CharClass) -> u32 {
+        match class {
+            CharClass::Lowercase => self.lowercase,
+            CharClass::Uppercase => self.uppercase,
+            CharClass::Digit => self.digit,
+            CharClass::Symbol => self.symbol,
+        }
+    }
+}
+
+/// Which letter cases a password may contain.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LetterCase {
+    #[default]
+    Mixed,
+    Upper,
+    Lower,
+}
+
+/// Settings for a generated password.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordOptions {
+    /// Number of characters in the password.
+    pub length: usize,
+    /// Whether symbols are part of the character pool.
+    pub include_symbols: bool,
+    /// Guarantee at least one character from every enabled class.
+    pub require_classes: bool,
+    /// Leave out characters that are easy to misread, like `l`, `1` and `O`.
+    pub exclude_ambiguous: bool,
+    /// Minimum number of digits in the password.
+    pub min_digits: usize,
+    /// Minimum number of symbols in the password; requires `include_symbols`.
+    pub min_symbols: usize,
+    /// Exact characters to draw from instead of the built-in classes.
+    ///
+    /// Duplicates are ignored, and class minimums don't apply to a custom charset.
+    pub charset: Option<String>,
+    /// Characters to remove from the pool, whether built-in or custom.
+    pub exclude: String,
+    /// Reject passwords with three or more identical characters in a row, like `aaa`.
+    pub no_repeats: bool,
+    /// Reject passwords with three or more consecutive letters or digits, like `abc` or `321`.
+    pub no_sequences: bool,
+    /// Restrict letters to one case; digits and symbols are unaffected.
+    pub case: LetterCase,
+    /// Reject passwords containing any of these words, ignoring case and look-alikes like `p4ss`.
+    pub avoid: Vec<String>,
+    /// Reject passwords containing a handful of very common words, like `password` or `qwerty`.
+    pub avoid_common_words: bool,
+    /// Make the first and last characters letters or digits, for systems that reject a leading or trailing symbol.
+    pub alphanumeric_ends: bool,
+    /// Reject passwords that don't match this regex, which may use lookahead like `(?=.*\d)(?=.*[A-Z])`.
+    pub require_regex: Option<String>,
+    /// Draw classes by these weights instead of every pool character being equally likely.
+    ///
+    /// Ignored with a custom charset, which has no classes to weigh.
+    pub weights: Option<ClassWeights>,
+    /// Only use letters, digits and `-._~`, which are safe in URLs and shell single quotes.
+    ///
+    /// Symbols are then drawn from those four; a custom charset is filtered down to them.
+    pub url_safe: bool,
+}
+
+impl Default for PasswordOptions {
+    fn default() -> Self {
+        PasswordOptions {
+            length: 50,
+            include_symbols: true,
+            require_classes: false,
+            exclude_ambiguous: false,
+            min_digits: 0,
+            min_symbols: 0,
+            charset: None,
+            exclude: String::new(),
+            no_repeats: false,
+            no_sequences: false,
+            case: LetterCase::Mixed,
+            avoid: Vec::new(),
+            avoid_common_words: false,
+            alphanumeric_ends: false,
+            require_regex: None,
+            weights: None,
+            url_safe: false,
+        }
+    }
+}
+
+impl PasswordOptions {
+    fn classes(&self) -> Vec<(CharClass, Vec<char>)> {
+        if self.charset.is_some() {
+            return Vec::new();
+        }
+        let mut classes = Vec::new();
+        if self.case != LetterCase::Upper {
+            classes.push(CharClass::Lowercase);
+        }
+        if self.case != LetterCase::Lower {
+            classes.push(CharClass::Uppercase);
+        }
+        classes.push(CharClass::Digit);
+        if self.include_symbols {
+            classes.push(CharClass::Symbol);
+        }
+        classes
+            .into_iter()
+            .map(|class| {
+                let chars = match class {
+                    CharClass::Symbol if self.url_safe => URL_SAFE_SYMBOLS,
+                    class => class.chars(),
+                };
+                let chars = chars.chars().filter(|c| !self.is_excluded(*c)).collect();
+                (class, chars)
+            })
+            .collect()
+    }
+
+    /// Whether `c` is filtered out of the pool.
+    fn is_excluded(&self, c: char) -> bool {
+        let wrong_case = match self.case {
+            LetterCase::Mixed => false,
+            LetterCase::Upper => c.is_lowercase(),
+            LetterCase::Lower => c.is_uppercase(),
+        };
+        let unsafe_in_urls =
+            self.url_safe && !(c.is_ascii_alphanumeric() || URL_SAFE_SYMBOLS.contains(c));
+        wrong_case
+            || unsafe_in_urls
+            || (self.exclude_ambiguous && AMBIGUOUS.contains(c))
+            || self.exclude.contains(c)
+    }
+
+    /// Whether `password` passes the repeat, sequence, regex and avoided word checks.
+    fn accepts(&self, password: &[char], required: Option<&Regex>) -> bool {
+        let repeats = |w: &[char]| w[0] == w[1] && w[1] == w[2];
+        let sequence = |w: &[char]| {
+            let step = |a: char, b: char| b as i64 - a as i64;
+            w.iter().all(|c| c.is_ascii_alphanumeric())
+                && step(w[0], w[1]).abs() == 1
+                && step(w[0], w[1]) == step(w[1], w[2])
+        };
+        if password
+            .windows(3)
+            .any(|w| (self.no_repeats && repeats(w)) || (self.no_sequences && sequence(w)))
+        {
+            return false;
+        }
+        if let Some(required) = required {
+            let mut text: String = password.iter().collect();
+            // A match that hits the backtracking limit counts as a miss
+            let matched = required.is_match(&text).unwrap_or(false);
+            text.zeroize();
+            if !matched {
+                return false;
+            }
+        }
+        if self.avoid.is_empty() && !self.avoid_common_words {
+            return true;
+        }
+
+        let mut folded: String = password.iter().map(|c| fold(*c)).collect();
+        let common = COMMON_WORDS.iter().filter(|_| self.avoid_common_words);
+        let found = self
+            .avoid
+            .iter()
+            .map(String::as_str)
+            .chain(common.copied())
+            .filter(|word| !word.is_empty())
+            .any(|word| folded.contains(&word.chars().map(fold).collect::<String>()));
+        folded.zeroize();
+        !found
+    }
+
+    /// How many characters of `class` the password must contain.
+    fn minimum(&self, class: CharClass) -> usize {
+        let minimum = match class {
+            CharClass::Digit => self.min_digits,
+            CharClass::Symbol => self.min_symbols,
+            _ => 0,
+        };
+        minimum.max(self.require_classes as usize)
+    }
+
+    /// The classes `weights` can draw from, with their characters and weights.
+    fn weighted_classes(&self, weights: ClassWeights) -> Vec<(Vec<char>, u32)> {
+        self.classes()
+            .into_iter()
+            .filter(|(_, chars)| !chars.is_empty())
+            .map(|(class, chars)| (chars, weights.of(class)))
+            .collect()
+    }
+
+    /// Bits of entropy in each freely drawn character.
+    ///
+    /// That's `log2` of the pool size, or with `weights`, the Shannon entropy of picking a class by
+    /// weight and then a character within it, which is lower for any uneven weighting.
+    pub fn bits_per_char(&self) -> f64 {
+        let Some(weights) = self.weights.filter(|_| self.charset.is_none()) else {
+            return estimate_entropy(1, self.pool().len());
+        };
+        let classes = self.weighted_classes(weights);
+        let total: u32 = classes.iter().map(|(_, weight)| weight).sum();
+        classes
+            .iter()
+            .filter(|(_, weight)| *weight > 0)
+            .map(|(chars, weight)| {
+                let p = *weight as f64 / total as f64;
+                p * ((chars.len() as f64).log2() - p.log2())
+            })
+            .sum()
+    }
+
+    /// How many ends `alphanumeric_ends` keeps to letters and digits: both, or the only character.
+    fn ends(&self) -> usize {
+        if self.alphanumeric_ends {
+            self.length.min(2)
+        } else {
+            0
+        }
+    }
+
+    /// The letters and digits of the pool, which the ends are drawn from with `alphanumeric_ends`.
+    fn end_pool(&self) -> Vec<char> {
+        self.pool()
+            .into_iter()
+            .filter(|c| c.is_alphanumeric())
+            .collect()
+    }
+
+    /// Bits of entropy in a password following these options.
+    ///
+    /// Ends kept to letters and digits count at `log2` of those alone, every other character at
+    /// `bits_per_char`.
+    pub fn entropy(&self) -> f64 {
+        let ends = self.ends();
+        (self.length - ends) as f64 * self.bits_per_char()
+            + estimate_entropy(ends, self.end_pool().len())
+    }
+
+    /// Shortest length at which `entropy` reaches `bits`, or `usize::MAX` if no length does.
+    pub fn length_for_entropy(&self, bits: f64) -> usize {
+        if bits <= 0.0 {
+            return 0;
+        }
+        let ends = if self.alphanumeric_ends { 2 } else { 0 };
+        let end_bits = estimate_entropy(ends, self.end_pool().len());
+        let free = ((bits - end_bits).max(0.0) / self.bits_per_char()).ceil();
+        // A pool of fewer than two characters gives no bits, and an infinite length saturates
+        ends.saturating_add(free as usize)
+    }
+
+    /// Every character a password may be drawn from.
+    pub fn pool(&self) -> Vec<char> {
+        match &self.charset {
+            Some(charset) => {
+                let mut pool: Vec<char> = Vec::new();
+                for c in charset.chars() {
+                    if !self.is_excluded(c) && !pool.contains(&c) {
+                        pool.push(c);
+                    }
+                }
+                pool
+            }
+            None => self
+                .classes()
+                .into_iter()
+                .flat_map(|(_, chars)| chars)
+                .collect(),
+        }
+    }
+}
+
+/// Lowercases `c` and maps digits and symbols that pass for letters, so `P@55` reads as `pass`.
+///
+/// `1` can stand for either `i` or `l`, so `l` folds to `i` too and `he11o` still reads as `hello`.
+fn fold(c: char) -> char {
+    match c.to_ascii_lowercase() {
+        '0' => 'o',
+        '1' | 'l' | '!' | '|' => 'i',
+        '3' => 'e',
+        '4' | '@' => 'a',
+        '5' | '$' => 's',
+        '7' => 't',
+        c => c,
+    }
+}
+
+/// Reasons a password can't be generated from the given options.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// A length of zero was requested, which would make an empty password.
+    ZeroLength,
+    /// The length can't fit the characters every required class reserves.
+    TooShort { length: usize, required: usize },
+    /// A minimum number of symbols was requested with symbols turned off.
+    SymbolsDisabled,
+    /// Every character of a class the password must contain was excluded.
+    ClassExcluded { class: &'static str },
+    /// The character pool has too few distinct characters to be random.
+    PoolTooSmall { size: usize },
+    /// No password drawn within the retry cap passed the rejection checks.
+    Unsatisfiable { attempts: usize },
+    /// A pattern used a letter or digit that isn't a placeholder.
+    UnknownPlaceholder { placeholder: char },
+    /// A pattern has no placeholders, so every password drawn from it would be the same.
+    NoPlaceholders,
+    /// The required regex doesn't compile.
+    InvalidRegex { reason: String },
+    /// Every class that has characters to draw from was weighted 0.
+    ZeroWeights,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ZeroLength => write!(f, "a password needs at least one character"),
+            Error::TooShort { length, required } => write!(
+                f,
+                "a length of {} is too short, at least {} characters are required",
+                length, required
+            ),
+            Error::SymbolsDisabled => write!(
+                f,
+                "a minimum number of symbols requires symbols to be enabled"
+            ),
+            Error::ClassExcluded { class } => write!(
+                f,
+                "every {} was excluded, but the password must contain one",
+                class
+            ),
+            Error::PoolTooSmall { size } => write!(
+                f,
+                "the character pool has {} distinct characters, at least 2 are required",
+                size
+            ),
+            Error::Unsatisfiable { attempts } => write!(
+                f,
+                "no password met every constraint after {} attempts",
+                attempts
+            ),
+            Error::UnknownPlaceholder { placeholder } => write!(
+                f,
+                "'{}' is not a pattern placeholder, use A, a, 9 or ! or escape it with a backslash",
+                placeholder
+            ),
+            Error::NoPlaceholders => {
+                write!(f, "a pattern needs at least one placeholder: A, a, 9 or !")
+            }
+            Error::InvalidRegex { reason } => write!(f, "invalid regex: {}", reason),
+            Error::ZeroWeights => write!(
+                f,
+                "every character class that can be drawn from has a weight of 0"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Generates a random password according to `options`, drawing from the operating system's CSPRNG.
+pub fn generate_password(options: &PasswordOptions) -> Result<String, Error> {
+    generate_password_with_rng(options, &mut OsRng)
+}
+
+/// Generates a random password according to `options`, drawing from `rng`.
+///
+/// Only cryptographically secure generators are accepted; tests can pass a seeded `StdRng`.
+pub fn generate_password_with_rng<R: Rng + CryptoRng + ?Sized>(
+    options: &PasswordOptions,
+    rng: &mut R,
+) -> Result<String, Error> {
+    Generator::new(options)?.next_password_with_rng(rng)
+}
+
+// A set of characters and the uniform draw of an index into it
+type Draw = (Vec<char>, Uniform<usize>);
+
+/// Draws passwords for one set of options, checking them and building the pool only once.
+///
+/// Use it instead of `generate_password` when generating many passwords with the same options.
+#[derive(Debug, Clone)]
+pub struct Generator {
+    options: PasswordOptions,
+    regex: Option<Regex>,
+    pool: Vec<char>,
+    pool_index: Uniform<usize>,
+    // Classes picked by weight, each with a uniform draw within it
+    weighted: Option<(WeightedIndex<u32>, Vec<Draw>)>,
+    // Each class's characters and draw, with how many of them a password must contain
+    reserved: Vec<(Vec<char>, Uniform<usize>, usize)>,
+    ends: usize,
+    end_pool: Vec<char>,
+    end_index: Uniform<usize>,
+}
+
+impl Generator {
+    /// Checks that `options` can produce a password and precomputes everything drawing one needs.
+    pub fn new(options: &PasswordOptions) -> Result<Self, Error> {
+        if options.length == 0 {
+            return Err(Error::ZeroLength);
+        }
+        if options.min_symbols > 0 && !options.include_symbols {
+            return Err(Error::SymbolsDisabled);
+        }
+        let regex = match &options.require_regex {
+            Some(pattern) => Some(Regex::new(pattern).map_err(|e| Error::InvalidRegex {
+                reason: e.to_string(),
+            })?),
+            None => None,
+        };
+        let classes = options.classes();
+        for (class, chars) in &classes {
+            if chars.is_empty() && options.minimum(*class) > 0 {
+                return Err(Error::ClassExcluded {
+                    class: class.name(),
+                });
+            }
+        }
+        // The ends are drawn separately, so the reserved characters must fit between them
+        let ends = options.ends();
+        let required: usize = classes
+            .iter()
+            .map(|(class, _)| options.minimum(*class))
+            .sum::<usize>()
+            + ends;
+        if options.length < required {
+            return Err(Error::TooShort {
+                length: options.length,
+                required,
+            });
+        }
+
+        // One combined pool, so every character is equally likely
+        let pool = options.pool();
+        if pool.len() < 2 {
+            return Err(Error::PoolTooSmall { size: pool.len() });
+        }
+
+        let end_pool = options.end_pool();
+        if ends > 0 && end_pool.is_empty() {
+            return Err(Error::ClassExcluded {
+                class: "letter or digit",
+            });
+        }
+
+        // Build each index distribution once instead of once per character
+        let pool_index = Uniform::new(0, pool.len());
+        let weighted = match options.weights.filter(|_| options.charset.is_none()) {
+            Some(weights) => {
+                let classes = options.weighted_classes(weights);
+                let class_index = WeightedIndex::new(classes.iter().map(|(_, weight)| *weight))
+                    .map_err(|_| Error::ZeroWeights)?;
+                let classes = classes
+                    .into_iter()
+                    .map(|(chars, _)| {
+                        let index = Uniform::new(0, chars.len());
+                        (chars, index)
+                    })
+                    .collect();
+                Some((class_index, classes))
+            }
+            None => None,
+        };
+        let end_index = Uniform::new(0, end_pool.len().max(1));
+        let reserved = classes
+            .into_iter()
+            .map(|(class, chars)| {
+                let index = Uniform::new(0, chars.len().max(1));
+                (chars, index, options.minimum(class))
+            })
+            .collect();
+
+        Ok(Generator {
+            options: options.clone(),
+            regex,
+            pool,
+            pool_index,
+            weighted,
+            reserved,
+            ends,
+            end_pool,
+            end_index,
+        })
+    }
+
+    /// Draws the next password from the operating system's CSPRNG.
+    pub fn next_password(&self) -> Result<String, Error> {
+        self.next_password_with_rng(&mut OsRng)
+    }
+
+    /// Draws the next password from `rng`.
+    pub fn next_password_with_rng<R: Rng + CryptoRng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> Result<String, Error> {
+        let length = self.options.length;
+        for _ in 0..MAX_ATTEMPTS {
+            // Reserve slots for each class's minimum, then fill the rest from the pool
+            let mut password: Vec<char> = Vec::with_capacity(length);
+            for (chars, index, minimum) in &self.reserved {
+                for _ in 0..*minimum {
+                    password.push(chars[index.sample(rng)]);
+                }
+            }
+            while password.len() < length - self.ends {
+                let c = match &self.weighted {
+                    Some((class_index, classes)) => {
+                        let (chars, index) = &classes[class_index.sample(rng)];
+                        chars[index.sample(rng)]
+                    }
+                    None => self.pool[self.pool_index.sample(rng)],
+                };
+                password.push(c);
+            }
+
+            // Shuffle so the reserved characters don't always lead
+            password.shuffle(rng);
+            if self.ends > 0 {
+                password.insert(0, self.end_pool[self.end_index.sample(rng)]);
+            }
+            if self.ends > 1 {
+                password.push(self.end_pool[self.end_index.sample(rng)]);
+            }
+
+            if !self.options.accepts(&password, self.regex.as_ref()) {
+                password.zeroize();
+                continue;
+            }
+
+            // Size the string up front so growing it never leaves stray copies behind
+            let mut output = String::with_capacity(password.iter().map(|c| c.len_utf8()).sum());
+            output.extend(password.iter());
+            password.zeroize();
+            return Ok(output);
+        }
+
+        Err(Error::Unsatisfiable {
+            attempts: MAX_ATTEMPTS,
+        })
+    }
+}
+
+/// Generates a numeric PIN of `length` digits, drawing from the operating system's CSPRNG.
+pub fn generate_pin(length: usize) -> String {
+    generate_pin_with_rng(length, &mut OsRng)
+}
+
+/// Generates a numeric PIN of `length` digits, drawing from `rng`.
+pub fn generate_pin_with_rng<R: Rng + CryptoRng + ?Sized>(length: usize, rng: &mut R) -> String {
+    (0..length)
+        .map(|_| char::from(b'0' + rng.gen_range(0..=9)))
+        .collect()
+}
+
+/// Bits of entropy in a PIN of `length` digits.
+pub fn pin_entropy(length: usize) -> f64 {
+    estimate_entropy(length, DIGITS.len())
+}
+
+/// Inserts `separator` after every `every` characters of `password`, like `a8Kd-9Fj2-xQ7m`.
+///
+/// The separators are only for readability and add no entropy.
+pub fn group_password(password: &str, every: usize, separator: char) -> String {
+    let mut grouped = String::with_capacity(password.len() + password.len() / every.max(1));
+    for (i, c) in password.chars().enumerate() {
+        if i > 0 && every > 0 && i % every == 0 {
+            grouped.push(separator);
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+/// Bits of entropy in a password of `length` characters drawn from `pool_size` characters.
+///
+/// An empty password or a pool of fewer than two characters carries none.
+pub fn estimate_entropy(length: usize, pool_size: usize) -> f64 {
+    if length == 0 || pool_size < 2 {
+        return 0.0;
+    }
+    length as f64 * (pool_size as f64).log2()
+}
+
+/// Zeroes the password's memory and returns how many bytes it held.
+///
+/// Unlike a plain overwrite, the wipe can't be optimised away even though the buffer is dropped right after.
+pub fn destroy_password(mut password: Zeroizing<String>) -> usize {
+    let len = password.len();
+    password.zeroize();
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashSet;
+
+    #[test]
+    fn zero_length_is_rejected() {
+        let options = PasswordOptions {
+            length: 0,
+            ..PasswordOptions::default()
+        };
+        assert_eq!(generate_password(&options), Err(Error::ZeroLength));
+        assert!(matches!(Generator::new(&options), Err(Error::ZeroLength)));
+    }
+
+    #[test]
+    fn empty_pool_is_rejected() {
+        let empty = PasswordOptions {
+            charset: Some(String::new()),
+            ..PasswordOptions::default()
+        };
+        assert_eq!(
+            generate_password(&empty),
+            Err(Error::PoolTooSmall { size: 0 })
+        );
+        let emptied = PasswordOptions {
+            charset: Some("ab".to_string()),
+            exclude: "ab".to_string(),
+            ..PasswordOptions::default()
+        };
+        assert_eq!(
+            generate_password(&emptied),
+            Err(Error::PoolTooSmall { size: 0 })
+        );
+    }
+
+    #[test]
+    fn empty_inputs_are_safe_everywhere_else() {
+        assert_eq!(estimate_entropy(0, 90), 0.0);
+        assert_eq!(estimate_entropy(10, 0), 0.0);
+        assert_eq!(mask_password("", Reveal::default()), "");
+        assert_eq!(group_password("", 4, '-'), "");
+        assert_eq!(generate_pin(0), "");
+        assert_eq!(destroy_password(Zeroizing::new(String::new())), 0);
+    }
+
+    fn seeded() -> StdRng {
+        StdRng::seed_from_u64(0x5eed)
+    }
+
+    #[test]
+    fn symbols_are_drawn_in_proportion_to_the_pool() {
+        let options = PasswordOptions::default();
+        let generator = Generator::new(&options).unwrap();
+        let mut rng = seeded();
+        let mut symbols = 0;
+        let mut total = 0;
+        while total < 10_000 {
+            let password = generator.next_password_with_rng(&mut rng).unwrap();
+            symbols += password.chars().filter(|c| SYMBOLS.contains(*c)).count();
+            total += password.chars().count();
+        }
+        // 28 of the 90 pool characters are symbols, about 31%
+        let ratio = symbols as f64 / total as f64;
+        assert!((0.28..0.34).contains(&ratio), "symbol ratio {}", ratio);
+    }
+
+    /// Whether `password` has a lowercase letter, an uppercase letter, a digit and, with `symbols`, a symbol.
+    fn has_every_class(password: &str, symbols: bool) -> bool {
+        let has = |class: &str| password.chars().any(|c| class.contains(c));
+        has(LOWERCASE) && has(UPPERCASE) && has(DIGITS) && (!symbols || has(SYMBOLS))
+    }
+
+    #[test]
+    fn required_classes_always_appear() {
+        let mut rng = seeded();
+        for include_symbols in [true, false] {
+            let options = PasswordOptions {
+                length: 12,
+                include_symbols,
+                require_classes: true,
+                ..PasswordOptions::default()
+            };
+            for _ in 0..1000 {
+                let password = generate_password_with_rng(&options, &mut rng).unwrap();
+                assert_eq!(password.chars().count(), 12);
+                assert!(has_every_class(&password, include_symbols), "{}", password);
+            }
+        }
+    }
+
+    #[test]
+    fn entropy_follows_the_pool_size() {
+        let with_symbols = PasswordOptions::default();
+        let without_symbols = PasswordOptions {
+            include_symbols: false,
+            ..PasswordOptions::default()
+        };
+        assert_eq!(with_symbols.pool().len(), 90);
+        assert_eq!(without_symbols.pool().len(), 62);
+        // 50 * log2(90) and 50 * log2(62)
+        assert!((estimate_entropy(50, 90) - 324.59).abs() < 0.01);
+        assert!((estimate_entropy(50, 62) - 297.70).abs() < 0.01);
+        assert!((with_symbols.bits_per_char() * 50.0 - estimate_entropy(50, 90)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ambiguous_characters_never_appear() {
+        let options = PasswordOptions {
+            exclude_ambiguous: true,
+            ..PasswordOptions::default()
+        };
+        let generator = Generator::new(&options).unwrap();
+        let mut rng = seeded();
+        for _ in 0..500 {
+            let password = generator.next_password_with_rng(&mut rng).unwrap();
+            assert!(
+                !password.contains(|c| AMBIGUOUS.contains(c)),
+                "{}",
+                password
+            );
+        }
+        assert_eq!(options.pool().len(), 85);
+    }
+
+    #[test]
+    fn minimum_digits_and_symbols_are_met() {
+        let options = PasswordOptions {
+            length: 12,
+            min_digits: 3,
+            min_symbols: 2,
+            ..PasswordOptions::default()
+        };
+        let mut rng = seeded();
+        for _ in 0..1000 {
+            let password = generate_password_with_rng(&options, &mut rng).unwrap();
+            assert_eq!(password.chars().count(), 12);
+            assert!(password.chars().filter(|c| DIGITS.contains(*c)).count() >= 3);
+            assert!(password.chars().filter(|c| SYMBOLS.contains(*c)).count() >= 2);
+        }
+    }
+
+    #[test]
+    fn minimums_longer_than_the_password_are_rejected() {
+        let options = PasswordOptions {
+            length: 4,
+            min_digits: 3,
+            min_symbols: 2,
+            ..PasswordOptions::default()
+        };
+        assert_eq!(
+            generate_password(&options),
+            Err(Error::TooShort {
+                length: 4,
+                required: 5
+            })
+        );
+        let no_symbols = PasswordOptions {
+            min_symbols: 1,
+            include_symbols: false,
+            ..PasswordOptions::default()
+        };
+        assert_eq!(generate_password(&no_symbols), Err(Error::SymbolsDisabled));
+    }
+
+    #[test]
+    fn pins_are_digits_of_the_requested_length() {
+        let mut rng = seeded();
+        for length in [4, 6, 20] {
+            for _ in 0..100 {
+                let pin = generate_pin_with_rng(length, &mut rng);
+                assert_eq!(pin.len(), length);
+                assert!(pin.chars().all(|c| c.is_ascii_digit()), "{}", pin);
+            }
+        }
+        assert!((pin_entropy(6) - 6.0 * 10f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn excluded_characters_never_appear() {
+        let mut rng = seeded();
+        let cases = [
+            PasswordOptions {
+                exclude: ";'aZ9".to_string(),
+                ..PasswordOptions::default()
+            },
+            PasswordOptions {
+                exclude: "aZ9".to_string(),
+                include_symbols: false,
+                ..PasswordOptions::default()
+            },
+            PasswordOptions {
+                charset: Some("abcdef;'".to_string()),
+                exclude: ";'a".to_string(),
+                ..PasswordOptions::default()
+            },
+        ];
+        for options in cases {
+            assert!(!options.pool().iter().any(|c| options.exclude.contains(*c)));
+            for _ in 0..200 {
+                let password = generate_password_with_rng(&options, &mut rng).unwrap();
+                assert!(
+                    !password.contains(|c| options.exclude.contains(c)),
+                    "{}",
+                    password
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn excluding_the_whole_pool_is_rejected() {
+        let options = PasswordOptions {
+            include_symbols: false,
+            exclude: format!("{}{}{}", LOWERCASE, UPPERCASE, DIGITS),
+            ..PasswordOptions::default()
+        };
+        assert_eq!(
+            generate_password(&options),
+            Err(Error::PoolTooSmall { size: 0 })
+        );
+        let one_left = PasswordOptions {
+            charset: Some("abc".to_string()),
+            exclude: "ab".to_string(),
+            ..PasswordOptions::default()
+        };
+        assert_eq!(
+            generate_password(&one_left),
+            Err(Error::PoolTooSmall { size: 1 })
+        );
+    }
+
+    #[test]
+    fn separators_go_every_n_characters() {
+        assert_eq!(group_password("abcdefghij", 4, '-'), "abcd-efgh-ij");
+        assert_eq!(group_password("abcdefgh", 4, '-'), "abcd-efgh");
+        assert_eq!(group_password("abcdefgh", 1, ' '), "a b c d e f g h");
+        assert_eq!(group_password("abcdefgh", 8, '-'), "abcdefgh");
+        assert_eq!(group_password("abcdefgh", 100, '-'), "abcdefgh");
+        assert_eq!(group_password("abcdefgh", 0, '-'), "abcdefgh");
+        assert_eq!(group_password("äöüßéè", 2, '·'), "äö·üß·éè");
+    }
+
+    fn chars(password: &str) -> Vec<char> {
+        password.chars().collect()
+    }
+
+    #[test]
+    fn repeats_and_sequences_are_rejected() {
+        let no_repeats = PasswordOptions {
+            no_repeats: true,
+            ..PasswordOptions::default()
+        };
+        assert!(!no_repeats.accepts(&chars("xaaay"), None));
+        assert!(!no_repeats.accepts(&chars("!!!"), None));
+        assert!(no_repeats.accepts(&chars("aabba"), None));
+        assert!(no_repeats.accepts(&chars("abcd"), None));
+
+        let no_sequences = PasswordOptions {
+            no_sequences: true,
+            ..PasswordOptions::default()
+        };
+        for password in ["xabc", "321y", "XYZ", "a789"] {
+            assert!(
+                !no_sequences.accepts(&chars(password), None),
+                "{}",
+                password
+            );
+        }
+        // Steps must match and stay within letters and digits
+        for password in ["abd", "aba", "9:;", "aaa", "yz{"] {
+            assert!(no_sequences.accepts(&chars(password), None), "{}", password);
+        }
+    }
+
+    #[test]
+    fn rejections_hold_across_many_passwords() {
+        let options = PasswordOptions {
+            length: 30,
+            no_repeats: true,
+            no_sequences: true,
+            include_symbols: false,
+            ..PasswordOptions::default()
+        };
+        let mut rng = seeded();
+        for _ in 0..500 {
+            let password = generate_password_with_rng(&options, &mut rng).unwrap();
+            assert!(options.accepts(&chars(&password), None), "{}", password);
+        }
+    }
+
+    #[test]
+    fn two_character_charset_without_repeats_is_unsatisfiable() {
+        let options = PasswordOptions {
+            length: 100,
+            charset: Some("ab".to_string()),
+            no_repeats: true,
+            ..PasswordOptions::default()
+        };
+        assert_eq!(
+            generate_password_with_rng(&options, &mut seeded()),
+            Err(Error::Unsatisfiable {
+                attempts: MAX_ATTEMPTS
+            })
+        );
+    }
+
+    #[test]
+    fn single_case_never_draws_the_other_case() {
+        let mut rng = seeded();
+        for (case, wrong) in [
+            (LetterCase::Upper, LOWERCASE),
+            (LetterCase::Lower, UPPERCASE),
+        ] {
+            let options = PasswordOptions {
+                case,
+                require_classes: true,
+                ..PasswordOptions::default()
+            };
+            // One letter class, digits and symbols: 26 + 10 + 28
+            assert_eq!(options.pool().len(), 64);
+            assert!((options.bits_per_char() - 6.0).abs() < 1e-9);
+            for _ in 0..200 {
+                let password = generate_password_with_rng(&options, &mut rng).unwrap();
+                assert!(!password.contains(|c| wrong.contains(c)), "{}", password);
+            }
+        }
+        let no_symbols = PasswordOptions {
+            case: LetterCase::Lower,
+            include_symbols: false,
+            ..PasswordOptions::default()
+        };
+        assert_eq!(no_symbols.pool().len(), 36);
+    }
+
+    #[test]
+    fn length_for_entropy_is_the_shortest_that_reaches_the_target() {
+        let pool = |include_symbols| PasswordOptions {
+            include_symbols,
+            ..PasswordOptions::default()
+        };
+        assert_eq!(pool(true).pool().len(), 90);
+        assert_eq!(pool(false).pool().len(), 62);
+        // The README's example: 128 bits is 20 characters with symbols and 22 without
+        assert_eq!(pool(true).length_for_entropy(128.0), 20);
+        assert_eq!(pool(false).length_for_entropy(128.0), 22);
+        for include_symbols in [true, false] {
+            let options = pool(include_symbols);
+            let size = options.pool().len();
+            for bits in [40.0, 64.0, 80.0, 100.0, 128.0, 256.0] {
+                let length = options.length_for_entropy(bits);
+                assert!(estimate_entropy(length, size) >= bits);
+                assert!(estimate_entropy(length - 1, size) < bits, "{} bits", bits);
+            }
+        }
+    }
+
+    #[test]
+    fn alphanumeric_ends_count_at_the_letters_and_digits_alone() {
+        let options = PasswordOptions {
+            length: 20,
+            alphanumeric_ends: true,
+            ..PasswordOptions::default()
+        };
+        let expected = estimate_entropy(18, 90) + estimate_entropy(2, 62);
+        assert!((options.entropy() - expected).abs() < 1e-9);
+        assert!(options.entropy() < estimate_entropy(20, 90) - 1.0);
+        let plain = PasswordOptions {
+            alphanumeric_ends: false,
+            ..options.clone()
+        };
+        assert!((plain.entropy() - estimate_entropy(20, 90)).abs() < 1e-9);
+
+        for bits in [64.0, 100.0, 128.0, 256.0] {
+            let length = options.length_for_entropy(bits);
+            let at = |length| {
+                PasswordOptions {
+                    length,
+                    ..options.clone()
+                }
+                .entropy()
+            };
+            assert!(at(length) >= bits && at(length - 1) < bits, "{} bits", bits);
+        }
+    }
+
+    #[test]
+    fn alphanumeric_ends_keep_symbols_off_both_ends() {
+        let mut rng = seeded();
+        for length in [1, 2, 3, 20] {
+            let options = PasswordOptions {
+                length,
+                alphanumeric_ends: true,
+                min_symbols: length.saturating_sub(2),
+                ..PasswordOptions::default()
+            };
+            for _ in 0..200 {
+                let password = chars(&generate_password_with_rng(&options, &mut rng).unwrap());
+                assert_eq!(password.len(), length);
+                assert!(password[0].is_ascii_alphanumeric());
+                assert!(password[length - 1].is_ascii_alphanumeric());
+                // Everything between the ends is a symbol, so the minimum is met between them
+                assert!(password[1..length.max(2) - 1]
+                    .iter()
+                    .all(|c| SYMBOLS.contains(*c)));
+            }
+        }
+
+        let symbols_only = PasswordOptions {
+            charset: Some("!@#".to_string()),
+            alphanumeric_ends: true,
+            ..PasswordOptions::default()
+        };
+        assert_eq!(
+            generate_password(&symbols_only),
+            Err(Error::ClassExcluded {
+                class: "letter or digit"
+            })
+        );
+        let no_room = PasswordOptions {
+            length: 4,
+            min_symbols: 3,
+            alphanumeric_ends: true,
+            ..PasswordOptions::default()
+        };
+        assert!(matches!(
+            generate_password(&no_room),
+            Err(Error::TooShort { .. })
+        ));
+    }
+
+    #[test]
+    fn required_regex_with_lookaheads_is_always_matched() {
+        let mut rng = seeded();
+        let options = PasswordOptions {
+            length: 12,
+            require_regex: Some(r"^(?=.*\d)(?=.*[A-Z])(?=.*[@#$%])[A-Za-z]".to_string()),
+            ..PasswordOptions::default()
+        };
+        let generator = Generator::new(&options).unwrap();
+        for _ in 0..200 {
+            let password = generator.next_password_with_rng(&mut rng).unwrap();
+            assert!(password.starts_with(|c: char| c.is_ascii_alphabetic()));
+            assert!(
+                password.contains(|c: char| c.is_ascii_digit()),
+                "{}",
+                password
+            );
+            assert!(password.contains(|c: char| c.is_ascii_uppercase()));
+            assert!(password.contains(|c: char| "@#$%".contains(c)));
+        }
+
+        let invalid = PasswordOptions {
+            require_regex: Some("(?=unclosed".to_string()),
+            ..PasswordOptions::default()
+        };
+        assert!(matches!(
+            Generator::new(&invalid),
+            Err(Error::InvalidRegex { .. })
+        ));
+        let impossible = PasswordOptions {
+            include_symbols: false,
+            require_regex: Some(r"(?=.*[@#$%])".to_string()),
+            ..PasswordOptions::default()
+        };
+        assert!(matches!(
+            generate_password(&impossible),
+            Err(Error::Unsatisfiable { .. })
+        ));
+    }
+
+    #[test]
+    fn classes_are_drawn_in_proportion_to_their_weights() {
+        let mut rng = seeded();
+        let options = PasswordOptions {
+            length: 100,
+            weights: Some(ClassWeights {
+                lowercase: 4,
+                uppercase: 2,
+                digit: 0,
+                symbol: 2,
+            }),
+            ..PasswordOptions::default()
+        };
+        let generator = Generator::new(&options).unwrap();
+        let mut counts = [0usize; 4];
+        for _ in 0..100 {
+            for c in generator.next_password_with_rng(&mut rng).unwrap().chars() {
+                let class = match c {
+                    'a'..='z' => 0,
+                    'A'..='Z' => 1,
+                    '0'..='9' => 2,
+                    _ => 3,
+                };
+                counts[class] += 1;
+            }
+        }
+        // Within about four standard deviations over 10,000 characters
+        let ratios = counts.map(|count| count as f64 / 10_000.0);
+        for (ratio, expected) in ratios.iter().zip([0.5, 0.25, 0.0, 0.25]) {
+            assert!((ratio - expected).abs() < 0.02, "{:?}", ratios);
+        }
+        assert_eq!(counts[2], 0);
+
+        let zero = PasswordOptions {
+            weights: Some(ClassWeights {
+                lowercase: 0,
+                uppercase: 0,
+                digit: 0,
+                symbol: 0,
+            }),
+            ..PasswordOptions::default()
+        };
+        assert!(matches!(Generator::new(&zero), Err(Error::ZeroWeights)));
+    }
+
+    #[test]
+    fn url_safe_passwords_only_use_unreserved_characters() {
+        let mut rng = seeded();
+        let unreserved = Regex::new(r"^[A-Za-z0-9._~-]+$").unwrap();
+        let cases = [
+            PasswordOptions {
+                url_safe: true,
+                min_symbols: 4,
+                ..PasswordOptions::default()
+            },
+            PasswordOptions {
+                url_safe: true,
+                charset: Some("ab/?#&=+%~.".to_string()),
+                ..PasswordOptions::default()
+            },
+        ];
+        for options in cases {
+            for _ in 0..200 {
+                let password = generate_password_with_rng(&options, &mut rng).unwrap();
+                assert!(unreserved.is_match(&password).unwrap(), "{}", password);
+            }
+        }
+        let url_safe = PasswordOptions {
+            url_safe: true,
+            ..PasswordOptions::default()
+        };
+        assert_eq!(url_safe.pool().len(), 66);
+    }
+
+    #[test]
+    fn one_generator_keeps_giving_valid_varied_passwords() {
+        let options = PasswordOptions {
+            length: 16,
+            require_classes: true,
+            no_repeats: true,
+            ..PasswordOptions::default()
+        };
+        let generator = Generator::new(&options).unwrap();
+        let pool = options.pool();
+        let mut seen = HashSet::new();
+        for _ in 0..500 {
+            let password = generator.next_password().unwrap();
+            assert_eq!(password.chars().count(), 16);
+            assert!(password.chars().all(|c| pool.contains(&c)));
+            assert!(has_every_class(&password, true), "{}", password);
+            assert!(options.accepts(&chars(&password), None));
+            seen.insert(password);
+        }
+        assert_eq!(seen.len(), 500);
+    }
+
+    #[test]
+    fn look_alike_spellings_of_avoided_words_are_rejected() {
+        let avoid = |words: &[&str]| PasswordOptions {
+            avoid: words.iter().map(|word| word.to_string()).collect(),
+            ..PasswordOptions::default()
+        };
+        let common = PasswordOptions {
+            avoid_common_words: true,
+            ..PasswordOptions::default()
+        };
+        assert!(!avoid(&["password"]).accepts(&chars("xP4ssw0rdx"), None));
+        assert!(!avoid(&["hello"]).accepts(&chars("ab-he11o-cd"), None));
+        assert!(!common.accepts(&chars("7Ab;QWERTYz9"), None));
+        assert!(!common.accepts(&chars("x1etmeinx"), None));
+        assert!(!common.accepts(&chars("xLetme1nx"), None));
+        assert!(avoid(&["password"]).accepts(&chars("xP4sswor"), None));
+        assert!(common.accepts(&chars("Zx9;qwertZ"), None));
+    }
+
+    #[test]
+    fn avoiding_every_character_is_unsatisfiable() {
+        let options = PasswordOptions {
+            charset: Some("ab".to_string()),
+            avoid: vec!["a".to_string(), "B".to_string()],
+            ..PasswordOptions::default()
+        };
+        assert_eq!(
+            generate_password(&options),
+            Err(Error::Unsatisfiable {
+                attempts: MAX_ATTEMPTS
+            })
+        );
+    }
+}
@@ -1,65 +1,516 @@
 use arboard::Clipboard;
-use clap::Parser;
-use rand::distributions::{Alphanumeric, Distribution, Uniform};
-use rand::{thread_rng, Rng};
-use std::io::{self, Write};
+use audit::AuditLog;
+use breach::BreachList;
+use clap::{CommandFactory, Parser};
+use cli::{
+    Cli, Command, Format, Length, OutputArgs, PasswordArgs, RngSource, LENGTH_RANGE,
+    RANDOM_LENGTH_RANGE,
+};
+use clipboard::{
+    clear_clipboard, clipboard_backend, clipboard_hint, still_on_clipboard, SecretClipboard,
+};
+use config::Config;
+use error::AppError;
+use log::{debug, info, LevelFilter};
+use pwgen::{
+    classify_strength, derive_key, destroy_password, generate_from_pattern_with_rng,
+    generate_hybrid_with_rng, generate_passphrase_with_rng, generate_password_with_rng,
+    generate_pin_with_rng, generate_pronounceable_with_rng, group_password, hybrid_entropy,
+    mask_password, passphrase_entropy, password_entropy, pattern_entropy, pin_entropy,
+    pronounceable_entropy, Capitalization, Generator, LetterCase, PasswordOptions, Reveal,
+    Separator,
+};
+use rand::Rng;
+use rng::SecretRng;
+use serde::Serialize;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::mem;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::panic;
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use terminal_size::{terminal_size, Width};
+use zeroize::Zeroizing;
 
-/// Generate a random password
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Args {
-    /// Ask for password length and symbol preference
-    #[arg(long)]
-    ask: bool,
+mod audit;
+mod breach;
+mod cli;
+mod clipboard;
+mod config;
+mod error;
+mod rng;
+mod stats;
+
+/// What kind of secret to generate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Mode {
+    Password(PasswordOptions),
+    Pattern(String),
+    Passphrase {
+        words: usize,
+        separator: Separator,
+        capitalization: Capitalization,
+    },
+    Pronounceable(usize),
+    Hybrid,
+    Pin(usize),
+}
+
+impl Mode {
+    /// The subcommand, or `pattern`, that produces this kind of secret.
+    fn name(&self) -> &'static str {
+        match self {
+            Mode::Password(_) => "password",
+            Mode::Pattern(_) => "pattern",
+            Mode::Passphrase { .. } => "passphrase",
+            Mode::Pronounceable(_) => "pronounceable",
+            Mode::Hybrid => "hybrid",
+            Mode::Pin(_) => "pin",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonOutput<'a> {
+    password: &'a str,
+    length: usize,
+    entropy_bits: f64,
+    symbols: bool,
+}
+
+const DEFAULT_LENGTH: usize = 50;
+const DEFAULT_TIMEOUT: u64 = 15;
+/// Cells in the `--progress` countdown bar.
+const PROGRESS_WIDTH: usize = 30;
+/// Opening of the `{{pw:N}}` placeholder filled in by `--stdin-template`.
+const TEMPLATE_TOKEN: &str = "{{pw:";
+/// How many secrets to draw before giving up when each one is in the `--check-breached` list.
+const BREACH_ATTEMPTS: usize = 100;
+
+/// Reads one line of input, treating a closed stdin as an error rather than an empty answer.
+fn read_answer(answer: &mut String) -> io::Result<()> {
+    if io::stdin().read_line(answer)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "input ended before an answer was given",
+        ));
+    }
+    Ok(())
+}
+
+/// Prints build metadata as `key=value` lines, for bug reports and audits.
+fn version_info() {
+    let features: &[&str] = &[
+        #[cfg(feature = "autotype")]
+        "autotype",
+    ];
+    println!("version={}", env!("CARGO_PKG_VERSION"));
+    println!("commit={}", env!("PWGEN_COMMIT"));
+    println!("rustc={}", env!("PWGEN_RUSTC"));
+    println!("target={}", env!("PWGEN_TARGET"));
+    println!("build_time={}", env!("PWGEN_BUILD_TIME"));
+    println!("features={}", features.join(","));
+    println!("clipboard={}", clipboard_backend());
+}
+
+/// Types the password into whichever window has focus once `delay` seconds are up.
+#[cfg(feature = "autotype")]
+fn autotype(password: &str, delay: u64, quiet: bool) -> Result<(), AppError> {
+    use enigo::{Enigo, Keyboard, Settings};
+
+    if !quiet {
+        eprintln!(
+            "Typing the password in {} seconds, focus the target field now.",
+            delay
+        );
+    }
+    sleep(Duration::from_secs(delay));
+    let mut enigo = Enigo::new(&Settings::default()).map_err(io::Error::other)?;
+    enigo.text(password).map_err(io::Error::other)?;
+    if !quiet {
+        eprintln!("Password typed.");
+    }
+    Ok(())
+}
+
+/// Takes over an inherited file descriptor for writing, failing cleanly if it isn't open for writing.
+///
+/// The descriptor is duplicated rather than reopened through `/dev/fd`, so an append-mode file keeps
+/// appending and sockets and pipes work too.
+#[cfg(unix)]
+fn open_fd(fd: u32) -> Result<File, AppError> {
+    use std::os::fd::{AsRawFd, BorrowedFd, RawFd};
+
+    let not_open = || AppError::Config(format!("file descriptor {} isn't open", fd));
+    let raw = RawFd::try_from(fd).map_err(|_| not_open())?;
+    // SAFETY: the borrow only lives for the dup, which fails with EBADF if `raw` isn't open
+    let owned = unsafe { BorrowedFd::borrow_raw(raw) }
+        .try_clone_to_owned()
+        .map_err(|e| AppError::Config(format!("file descriptor {} isn't open: {}", fd, e)))?;
+    // Catch a read-only descriptor now, rather than at the first write with the password in hand
+    // SAFETY: F_GETFL only reads the flags of a descriptor `owned` keeps open
+    let flags = unsafe { libc::fcntl(owned.as_raw_fd(), libc::F_GETFL) };
+    if flags == -1 || flags & libc::O_ACCMODE == libc::O_RDONLY {
+        return Err(AppError::Config(format!(
+            "file descriptor {} isn't open for writing",
+            fd
+        )));
+    }
+    Ok(File::from(owned))
+}
+
+#[cfg(not(unix))]
+fn open_fd(_fd: u32) -> Result<File, AppError> {
+    Err(AppError::Config(
+        "--fd is only supported on Unix".to_string(),
+    ))
+}
+
+/// The directory `path` is in, which is the current one for a bare file name.
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+/// Creates `path` for `--output-file`, readable by its owner only, refusing to replace an existing file.
+fn create_output_file(path: &Path) -> Result<File, AppError> {
+    let dir = parent_dir(path);
+    if !dir.is_dir() {
+        return Err(AppError::Config(format!(
+            "can't create {}: {} isn't a directory",
+            path.display(),
+            dir.display()
+        )));
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    options
+        .open(path)
+        .map_err(|e| AppError::Config(format!("can't create {}: {}", path.display(), e)))
+}
+
+/// Applies one `[LENGTH] [symbols|no-symbols]` serve request on top of `defaults`.
+fn parse_request(line: &str, defaults: &PasswordOptions) -> Result<PasswordOptions, String> {
+    let mut options = defaults.clone();
+    let mut fields = line.split_whitespace().peekable();
+    if let Some(length) = fields.next_if(|field| field.starts_with(|c: char| c.is_ascii_digit())) {
+        options.length = cli::parse_length(length)?;
+    }
+    match fields.next() {
+        Some("symbols") => options.include_symbols = true,
+        Some("no-symbols") => options.include_symbols = false,
+        Some(field) => return Err(format!("unexpected field '{}'", field)),
+        None => {}
+    }
+    if let Some(field) = fields.next() {
+        return Err(format!("unexpected field '{}'", field));
+    }
+    Ok(options)
 }
 
-fn generate_password(length: usize, include_symbols: bool) -> String {
-    let mut rng = thread_rng();
+/// Reads requests from stdin and writes one password or `error: ...` line per request, until stdin closes.
+fn serve(defaults: &PasswordOptions, rng: &mut SecretRng) -> Result<(), AppError> {
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lines() {
+        let line = Zeroizing::new(line?);
+        let password = parse_request(&line, defaults).and_then(|options| {
+            generate_password_with_rng(&options, rng).map_err(|e| e.to_string())
+        });
+        match password {
+            Ok(password) => writeln!(stdout, "{}", *Zeroizing::new(password))?,
+            Err(e) => writeln!(stdout, "error: {}", e)?,
+        }
+        // Flush each answer so a parent process waiting on it isn't left hanging
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+/// Copies stdin to stdout, replacing every `{{pw:N}}` with its own fresh password of `N` characters.
+fn fill_template(defaults: &PasswordOptions, rng: &mut SecretRng) -> Result<(), AppError> {
+    let mut template = Zeroizing::new(String::new());
+    io::stdin().read_to_string(&mut template)?;
+    let filled = fill_tokens(&template, defaults, rng)?;
+    io::stdout().write_all(filled.as_bytes())?;
+    Ok(())
+}
 
-    let symbols: Vec<char> = "!@#$%^&*()-=_+[]{}|;:',.<>?/".chars().collect();
+/// Replaces every `{{pw:N}}` in `template` with its own fresh password of `N` characters.
+///
+/// Other text, including unfinished tokens, is copied as is.
+fn fill_tokens(
+    template: &str,
+    defaults: &PasswordOptions,
+    rng: &mut SecretRng,
+) -> Result<Zeroizing<String>, AppError> {
+    // Reserve room for the longest passwords, so the filled text is never reallocated and copied
+    let tokens = template.matches(TEMPLATE_TOKEN).count();
+    let mut filled = Zeroizing::new(String::with_capacity(
+        template.len() + tokens * LENGTH_RANGE.end(),
+    ));
+    let mut rest = template;
+    while let Some(start) = rest.find(TEMPLATE_TOKEN) {
+        let token = &rest[start + TEMPLATE_TOKEN.len()..];
+        let Some(end) = token.find("}}") else {
+            break;
+        };
+        let length = cli::parse_length(&token[..end]).map_err(|e| {
+            AppError::Config(format!(
+                "invalid {}{}}}}}: {}",
+                TEMPLATE_TOKEN,
+                &token[..end],
+                e
+            ))
+        })?;
+        let options = PasswordOptions {
+            length,
+            ..defaults.clone()
+        };
+        filled.push_str(&rest[..start]);
+        filled.push_str(&Zeroizing::new(generate_password_with_rng(&options, rng)?));
+        rest = &token[end + 2..];
+    }
+    filled.push_str(rest);
+    Ok(filled)
+}
+
+/// Erases the password line and the timer line below it, including any rows they wrapped onto.
+fn erase_lines(out: &mut impl Write, password_width: usize, timer_width: usize) -> io::Result<()> {
+    match terminal_size() {
+        Some((Width(columns), _)) if columns > 0 => {
+            let rows = |width: usize| width.max(1).div_ceil(columns as usize);
+            // The cursor sits on the timer's last row, so erase upwards from there
+            write!(out, "\r\x1b[2K")?;
+            for _ in 1..rows(password_width) + rows(timer_width) {
+                write!(out, "\x1b[1A\x1b[2K")?;
+            }
+        }
+        // Without a known width, overwrite both lines with generous padding
+        _ => {
+            write!(out, "\x1b[1A")?;
+            write!(out, "\r{}", " ".repeat(password_width + 100))?;
+            write!(out, "\r{}", " ".repeat(50))?;
+            write!(out, "\r")?;
+        }
+    }
+    out.flush()
+}
 
-    let dist = if include_symbols {
-        Uniform::new(0, 2) // 0 for alphanumeric, 1 for symbol
+/// Prints the password line, masked unless `--show` was given, and returns how many columns it takes.
+fn show_password(out: &mut impl Write, password: &str, args: &OutputArgs) -> io::Result<usize> {
+    let shown = if args.show {
+        Zeroizing::new(format!("Generated password: {}", password))
     } else {
-        Uniform::new(0, 1)
+        let reveal = args.reveal.map(Reveal::both).unwrap_or_default();
+        Zeroizing::new(format!(
+            "Generated password: {}",
+            mask_password(password, reveal)
+        ))
     };
+    writeln!(out, "{}", *shown)?;
+    Ok(shown.chars().count())
+}
 
-    (0..length)
-        .map(|_| {
-            if dist.sample(&mut rng) == 0 {
-                rng.sample(Alphanumeric) as char
-            } else {
-                let idx = rng.gen_range(0..symbols.len());
-                symbols[idx]
-            }
-        })
-        .collect()
+/// Shows the password and copies it, waiting for Enter in between with `accept`, for `--copy-on-accept`.
+///
+/// `copied` is set the moment the password is on the clipboard. Returns the width of the password line.
+fn show_then_copy(
+    clipboard: &mut impl SecretClipboard,
+    password: &str,
+    args: &OutputArgs,
+    accept: bool,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+    copied: &AtomicBool,
+) -> Result<usize, AppError> {
+    let shown_width = show_password(out, password, args)?;
+    if accept {
+        write!(out, "Press Enter to copy the password to the clipboard...")?;
+        out.flush()?;
+        // Input ending counts as accepting, but then no Enter moved the cursor down
+        if input.read_line(&mut String::new())? > 0 {
+            write!(out, "\x1b[1A")?;
+        }
+        write!(out, "\r\x1b[2K")?;
+        out.flush()?;
+    }
+    clipboard.copy(password)?;
+    copied.store(true, Ordering::SeqCst);
+    Ok(shown_width)
+}
+
+/// Offers fresh passwords until one is kept, copying each as it's shown, for `--interactive`.
+///
+/// Returns the width of the line the kept password is shown on.
+fn offer_another(
+    clipboard: &mut impl SecretClipboard,
+    password: &mut Zeroizing<String>,
+    mut shown_width: usize,
+    args: &OutputArgs,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+    generate: &mut impl FnMut() -> Result<Zeroizing<String>, AppError>,
+) -> Result<usize, AppError> {
+    let prompt = "Press r and Enter for another password, or Enter to keep this one: ";
+    loop {
+        write!(out, "{}", prompt)?;
+        out.flush()?;
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            // Input ended, so keep this one; no Enter moved the cursor on
+            write!(out, "\r\x1b[2K")?;
+            out.flush()?;
+            return Ok(shown_width);
+        }
+        // Step back onto the prompt line, which Enter moved past
+        write!(out, "\x1b[1A")?;
+        if !answer.trim().eq_ignore_ascii_case("r") {
+            write!(out, "\r\x1b[2K")?;
+            out.flush()?;
+            return Ok(shown_width);
+        }
+        erase_lines(out, shown_width, prompt.len() + answer.trim_end().len())?;
+        destroy_password(mem::replace(password, generate()?));
+        shown_width = show_password(out, password, args)?;
+        clipboard.copy(password)?;
+        info!("copied a regenerated password");
+    }
+}
+
+/// Prints the password in full when it can't be copied, with a hint on getting the clipboard working.
+fn print_instead(error: arboard::Error, password: Zeroizing<String>) -> Result<(), AppError> {
+    info!("clipboard unavailable: {}", error);
+    eprintln!("Clipboard unavailable ({}), printing to stdout.", error);
+    eprintln!("Hint: {}", clipboard_hint(&error));
+    println!("{}", *password);
+    destroy_password(password);
+    Ok(())
+}
+
+/// Set once the password is on the clipboard, so Ctrl-C only clears what pwgen put there.
+static COPIED: AtomicBool = AtomicBool::new(false);
+
+/// Clears the clipboard on Ctrl-C, from the moment the password is copied.
+fn clear_on_interrupt() {
+    let installed = ctrlc::set_handler(|| {
+        if !COPIED.load(Ordering::SeqCst) {
+            println!("\nInterrupted before anything was copied.");
+            process::exit(130);
+        }
+        let cleared =
+            Clipboard::new().and_then(|mut clipboard| SecretClipboard::clear(&mut clipboard));
+        match cleared {
+            Ok(()) => println!("\nInterrupted, password removed from clipboard."),
+            Err(e) => eprintln!(
+                "\nInterrupted, but the clipboard couldn't be cleared: {}",
+                e
+            ),
+        }
+        process::exit(130);
+    });
+    if let Err(e) = installed {
+        eprintln!("Warning: Ctrl-C won't clear the clipboard: {}", e);
+    }
+}
+
+/// Prints the strength of an existing password, prompting for it without echo if none was given.
+///
+/// The prompt is the safe path, since an argument ends up in shell history and the process list.
+fn check(password: Option<String>) -> Result<(), AppError> {
+    let password = match password {
+        Some(password) => Zeroizing::new(password),
+        None => prompt_secret("Password to check: ", "pass it as --check <PASSWORD>")?,
+    };
+    let bits = password_entropy(&password);
+    println!("Strength: {} ({:.0} bits)", classify_strength(bits), bits);
+    Ok(())
 }
 
-fn destroy_password(password: String) -> usize {
-    let mut bytes = password.into_bytes(); // Convert String to bytes.
-    let mut rng = thread_rng();
-    rng.fill(&mut bytes[0..]); // Overwrite bytes with garbage.
-    bytes.len()
+/// Replaces the default panic output, whose message can quote the password, e.g. when slicing it fails.
+///
+/// Only the location and, with `RUST_BACKTRACE` set, the backtrace are printed; neither holds any data.
+fn install_panic_hook() {
+    panic::set_hook(Box::new(|info| {
+        match info.location() {
+            Some(location) => eprintln!("Error: pwgen crashed at {}.", location),
+            None => eprintln!("Error: pwgen crashed."),
+        }
+        eprintln!("The panic message is withheld because it may contain the password.");
+        eprintln!("If a password was copied, clear the clipboard yourself.");
+        let backtrace = Backtrace::capture();
+        if backtrace.status() == BacktraceStatus::Captured {
+            eprintln!("{}", backtrace);
+        }
+    }));
 }
 
 fn main() {
-    let args = Args::parse();
+    install_panic_hook();
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+/// Reads a secret from a hidden prompt, explaining `fallback` if there's no terminal to prompt on.
+fn prompt_secret(prompt: &str, fallback: &str) -> io::Result<Zeroizing<String>> {
+    rpassword::prompt_password(prompt)
+        .map(Zeroizing::new)
+        .map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("can't prompt for the password ({}), {}", e, fallback),
+            )
+        })
+}
+
+/// A password following `--pattern` if one was given, otherwise one built from the other options.
+fn password_mode(
+    args: &PasswordArgs,
+    config: &Config,
+    quiet: bool,
+    rng: &mut SecretRng,
+) -> Result<Mode, AppError> {
+    match &args.pattern {
+        Some(pattern) => Ok(Mode::Pattern(pattern.clone())),
+        None => Ok(Mode::Password(password_options(args, config, quiet, rng)?)),
+    }
+}
 
+/// Builds the password options from `args`, prompting for them instead with `--ask`.
+///
+/// `--length random` is drawn from `rng`, so seeded and derived passwords reproduce their length too.
+fn password_options(
+    args: &PasswordArgs,
+    config: &Config,
+    quiet: bool,
+    rng: &mut SecretRng,
+) -> Result<PasswordOptions, AppError> {
     let (length, include_symbols) = if args.ask {
         let mut length_str = String::new();
         let length: usize; // Declare length outside the loop
         loop {
-            println!("Enter desired password length (minimum 10, maximum 100):");
-            io::stdin()
-                .read_line(&mut length_str)
-                .expect("Failed to read line");
-            length = match length_str.trim().parse() {
-                Ok(num) if (10..=100).contains(&num) => num,
-                _ => {
-                    println!("Invalid input. Please enter a number between 10 and 100.");
+            println!(
+                "Enter desired password length (minimum {}, maximum {}):",
+                LENGTH_RANGE.start(),
+                LENGTH_RANGE.end()
+            );
+            read_answer(&mut length_str)?;
+            length = match cli::parse_length(&length_str) {
+                Ok(num) => num,
+                Err(e) => {
+                    println!("Invalid input. {}", e);
                     length_str.clear();
                     continue;
                 }
@@ -69,52 +520,1000 @@ fn main() {
 
         let mut include_symbols_str = String::new();
         println!("Include symbols? (yes/no):");
-        io::stdin()
-            .read_line(&mut include_symbols_str)
-            .expect("Failed to read line");
+        read_answer(&mut include_symbols_str)?;
         let include_symbols: bool = include_symbols_str.trim().to_lowercase() == "yes";
 
         (length, include_symbols)
     } else {
-        (50, true) // Default values
+        // Flags win over the config file, which wins over the built-in defaults
+        let include_symbols = if args.symbols || args.no_symbols {
+            args.symbols
+        } else {
+            config.symbols.unwrap_or(true)
+        };
+        let length = match args.length {
+            Some(Length::Fixed(length)) => length,
+            Some(Length::Random) => {
+                let length = rng.gen_range(RANDOM_LENGTH_RANGE);
+                if !quiet {
+                    eprintln!("Using a random length of {}.", length);
+                }
+                length
+            }
+            None => config.length.unwrap_or(DEFAULT_LENGTH),
+        };
+        (length, include_symbols)
+    };
+
+    // A configured charset gives way to any flag that picks characters by class
+    let class_flags = args.ask
+        || args.symbols
+        || args.no_symbols
+        || args.require_classes
+        || args.min_digits > 0
+        || args.min_symbols > 0;
+    let charset = args
+        .charset
+        .clone()
+        .or_else(|| config.charset.clone().filter(|_| !class_flags));
+
+    let mut options = PasswordOptions {
+        length,
+        include_symbols,
+        require_classes: args.require_classes,
+        exclude_ambiguous: args.no_ambiguous || config.no_ambiguous.unwrap_or(false),
+        min_digits: args.min_digits,
+        min_symbols: args.min_symbols,
+        charset,
+        exclude: args.exclude.clone(),
+        no_repeats: args.no_repeats,
+        no_sequences: args.no_sequences,
+        case: if args.uppercase_only {
+            LetterCase::Upper
+        } else if args.lowercase_only {
+            LetterCase::Lower
+        } else {
+            LetterCase::Mixed
+        },
+        avoid: args.avoid_similar_to.clone(),
+        avoid_common_words: args.avoid_common_words,
+        alphanumeric_ends: args.no_start_end_symbol,
+        require_regex: args.require_regex.clone(),
+        weights: args.weight,
+        url_safe: args.url_safe,
+    };
+    if args.url_safe && !quiet {
+        eprintln!(
+            "URL-safe pool: {} characters, {:.2} bits per character.",
+            options.pool().len(),
+            options.bits_per_char()
+        );
+    }
+
+    // Pick the shortest length that reaches the target for the pool the other options produce
+    if let Some(bits) = args.bits {
+        let pool_size = options.pool().len();
+        if pool_size >= 2 {
+            let length = options
+                .length_for_entropy(bits as f64)
+                .max(*LENGTH_RANGE.start());
+            if length > *LENGTH_RANGE.end() {
+                return Err(AppError::Config(format!(
+                    "{} bits need {} characters from this pool, more than the maximum of {}",
+                    bits,
+                    length,
+                    LENGTH_RANGE.end()
+                )));
+            }
+            options.length = length;
+            if !quiet {
+                eprintln!(
+                    "Using a length of {} for {:.1} bits of entropy.",
+                    length,
+                    options.entropy()
+                );
+            }
+        }
+    }
+    Ok(options)
+}
+
+fn run() -> Result<(), AppError> {
+    let cli = Cli::parse();
+    if cli.version_info {
+        version_info();
+        return Ok(());
+    }
+    // Both are generated from the clap definition, so they can't fall behind the flags
+    match cli.command {
+        Some(Command::Completions { shell }) => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(shell, &mut command, name, &mut io::stdout());
+            return Ok(());
+        }
+        Some(Command::Manpage) => {
+            clap_mangen::Man::new(Cli::command()).render(&mut io::stdout())?;
+            return Ok(());
+        }
+        _ => {}
+    }
+    // Stay silent unless asked, while still honouring RUST_LOG
+    env_logger::Builder::new()
+        .filter_level(match cli.verbose {
+            0 => LevelFilter::Off,
+            1 => LevelFilter::Info,
+            2 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        })
+        .parse_default_env()
+        .init();
+    if let Some(password) = cli.check {
+        return check(password);
+    }
+    let config = config::load(cli.profile.as_deref()).map_err(AppError::Config)?;
+    let args = cli.output;
+    let timeout = args.timeout.or(config.timeout).unwrap_or(DEFAULT_TIMEOUT);
+
+    // Reproducible output has to be asked for by name, a stray --seed alone isn't enough
+    let seed = match cli.rng_source {
+        RngSource::Os if cli.seed.is_some() => {
+            return Err(AppError::Config(
+                "--seed only takes effect with --rng-source seeded".to_string(),
+            ))
+        }
+        RngSource::Os => None,
+        RngSource::Seeded => {
+            eprintln!(
+                "Warning: --rng-source seeded makes the output reproducible, never use it for real passwords."
+            );
+            cli.seed
+        }
+    };
+
+    // A derived password comes from the master password alone, down to a random length
+    let mut rng = match &cli.command {
+        Some(Command::Derive(derive)) => {
+            let master = prompt_secret("Master password: ", "use a terminal to enter it")?;
+            SecretRng::derived(&derive_key(&master, &derive.site, derive.counter))
+        }
+        _ => SecretRng::new(seed),
+    };
+    info!(
+        "RNG source: {}",
+        match rng {
+            SecretRng::Os(_) => "operating system CSPRNG",
+            SecretRng::Seeded(_) => "seeded StdRng (insecure)",
+            SecretRng::Derived(_) => "ChaCha20 keyed by the master password",
+        }
+    );
+
+    // Bare `pwgen` generates a password, as it did before there were subcommands
+    let mode = match cli.command {
+        None => password_mode(&cli.password, &config, args.quiet, &mut rng)?,
+        Some(Command::Password(password)) => {
+            password_mode(&password, &config, args.quiet, &mut rng)?
+        }
+        Some(Command::Passphrase(passphrase)) => Mode::Passphrase {
+            words: passphrase.words,
+            separator: passphrase.separator,
+            capitalization: if passphrase.capitalize {
+                Capitalization::Each
+            } else if passphrase.title_random {
+                Capitalization::RandomWord
+            } else {
+                Capitalization::None
+            },
+        },
+        Some(Command::Pronounceable(pronounceable)) => Mode::Pronounceable(
+            pronounceable
+                .length
+                .or(config.length)
+                .unwrap_or(DEFAULT_LENGTH),
+        ),
+        Some(Command::Hybrid) => Mode::Hybrid,
+        Some(Command::Pin(pin)) => Mode::Pin(pin.length),
+        Some(Command::Derive(derive)) => {
+            password_mode(&derive.password, &config, args.quiet, &mut rng)?
+        }
+        Some(Command::Completions { .. } | Command::Manpage) => unreachable!("handled above"),
+    };
+
+    if cli.show_pool {
+        let Mode::Password(options) = &mode else {
+            unreachable!("--show-pool conflicts with subcommands")
+        };
+        let pool: String = options.pool().into_iter().collect();
+        let size = pool.chars().count();
+        println!("Pool: {}", pool);
+        println!("Size: {} characters", size);
+        println!("Entropy: {:.2} bits per character", options.bits_per_char());
+        return Ok(());
+    }
+
+    if let Some(samples) = cli.stats {
+        let Mode::Password(options) = &mode else {
+            unreachable!("--stats conflicts with subcommands")
+        };
+        return stats::print(options, samples, &mut rng);
+    }
+
+    if cli.serve {
+        let Mode::Password(defaults) = &mode else {
+            unreachable!("--serve conflicts with subcommands")
+        };
+        return serve(defaults, &mut rng);
+    }
+
+    if cli.stdin_template {
+        let Mode::Password(defaults) = &mode else {
+            unreachable!("--stdin-template conflicts with subcommands")
+        };
+        return fill_template(defaults, &mut rng);
+    }
+
+    // Wait for the user before anything secret reaches the screen or clipboard
+    if args.confirm {
+        eprint!("Press Enter to generate and copy...");
+        io::stderr().flush()?;
+        read_answer(&mut String::new())?;
+    }
+
+    let entropy = match &mode {
+        Mode::Password(options) => options.entropy(),
+        Mode::Pattern(pattern) => pattern_entropy(pattern)?,
+        Mode::Passphrase {
+            words,
+            separator,
+            capitalization,
+        } => passphrase_entropy(*words, *separator, *capitalization),
+        Mode::Pronounceable(length) => pronounceable_entropy(*length),
+        Mode::Hybrid => hybrid_entropy(),
+        Mode::Pin(digits) => pin_entropy(*digits),
+    } + args.append_digits.map_or(0.0, pin_entropy);
+
+    let symbols = match &mode {
+        Mode::Password(options) => options.charset.is_none() && options.include_symbols,
+        Mode::Hybrid => true,
+        _ => false,
+    };
+    if let Mode::Password(options) = &mode {
+        info!(
+            "password length {}, pool of {} characters",
+            options.length,
+            options.pool().len()
+        );
+    }
+    info!("estimated entropy: {:.1} bits", entropy);
+
+    let mut breached = match &args.check_breached {
+        Some(path) => Some(BreachList::open(path)?),
+        None => None,
+    };
+    let mut audit = match &args.audit_log {
+        Some(path) => Some(AuditLog::open(path)?),
+        None => None,
+    };
+    let pool_size = match &mode {
+        Mode::Password(options) => Some(options.pool().len()),
+        _ => None,
+    };
+
+    // Check the options and build the pool once, rather than for every password of a batch
+    let generator = match &mode {
+        Mode::Password(options) => Some(Generator::new(options)?),
+        _ => None,
+    };
+
+    // Keep every password in a buffer that's wiped when dropped
+    let mut generate = || -> Result<Zeroizing<String>, AppError> {
+        for attempt in 1..=BREACH_ATTEMPTS {
+            let started = Instant::now();
+            let password = match &mode {
+                Mode::Password(_) => generator
+                    .as_ref()
+                    .expect("built for password mode")
+                    .next_password_with_rng(&mut rng)?,
+                Mode::Pattern(pattern) => generate_from_pattern_with_rng(pattern, &mut rng)?,
+                Mode::Passphrase {
+                    words,
+                    separator,
+                    capitalization,
+                } => generate_passphrase_with_rng(*words, *separator, *capitalization, &mut rng),
+                Mode::Pronounceable(length) => generate_pronounceable_with_rng(*length, &mut rng),
+                Mode::Hybrid => generate_hybrid_with_rng(&mut rng),
+                Mode::Pin(digits) => generate_pin_with_rng(*digits, &mut rng),
+            };
+            let mut password = Zeroizing::new(password);
+            debug!("generated a secret in {:?}", started.elapsed());
+            if let Some(digits) = args.append_digits {
+                // Growing the password in place would leave an unwiped copy behind, so size a new one
+                let suffix = Zeroizing::new(generate_pin_with_rng(digits, &mut rng));
+                let mut suffixed = Zeroizing::new(String::with_capacity(password.len() + digits));
+                suffixed.push_str(&password);
+                suffixed.push_str(&suffix);
+                password = suffixed;
+            }
+            if let Some(every) = args.separator_every {
+                password = Zeroizing::new(group_password(&password, every, args.group_char));
+            }
+
+            // Check exactly what will be handed out, separators and all
+            let listed = match &mut breached {
+                Some(list) => list.contains(&password)?,
+                None => false,
+            };
+            if !listed {
+                if let Some(log) = &mut audit {
+                    log.record(mode.name(), password.chars().count(), pool_size, entropy)?;
+                }
+                return Ok(password);
+            }
+            debug!("attempt {}: the secret is in the breach list", attempt);
+        }
+        Err(AppError::Password(pwgen::Error::Unsatisfiable {
+            attempts: BREACH_ATTEMPTS,
+        }))
     };
 
+    // Emit one JSON object per password for scripts, without touching the clipboard
+    if args.format == Format::Json {
+        for _ in 0..args.count {
+            let password = generate()?;
+            let output = JsonOutput {
+                password: &password,
+                length: password.chars().count(),
+                entropy_bits: entropy,
+                symbols,
+            };
+            let json = Zeroizing::new(serde_json::to_string(&output).map_err(io::Error::from)?);
+            println!("{}", *json);
+            destroy_password(password);
+        }
+        return Ok(());
+    }
+
+    // Stream one JSON array for the whole batch, so memory stays flat however large it is
+    if args.format == Format::JsonBatch {
+        let mut stdout = io::stdout().lock();
+        write!(stdout, "[")?;
+        for i in 0..args.count {
+            if i > 0 {
+                write!(stdout, ",")?;
+            }
+            let password = generate()?;
+            let output = JsonOutput {
+                password: &password,
+                length: password.chars().count(),
+                entropy_bits: entropy,
+                symbols,
+            };
+            serde_json::to_writer(&mut stdout, &output).map_err(io::Error::from)?;
+            destroy_password(password);
+        }
+        writeln!(stdout, "]")?;
+        return Ok(());
+    }
+
+    #[cfg(feature = "autotype")]
+    if args.autotype {
+        let password = generate()?;
+        return autotype(&password, args.autotype_delay, args.quiet);
+    }
+
+    // Hand the passwords straight to another process, bypassing the terminal and clipboard
+    if let Some(fd) = args.fd {
+        let mut out = open_fd(fd)?;
+        for _ in 0..args.count {
+            let password = generate()?;
+            writeln!(out, "{}", *password)?;
+            destroy_password(password);
+        }
+        out.flush()?;
+        if !args.quiet {
+            eprintln!("Password written to file descriptor {}.", fd);
+        }
+        return Ok(());
+    }
+
+    // Write a provisioning batch to disk, synced before reporting success so a crash can't lose it
+    if let Some(path) = &args.output_file {
+        let mut out = create_output_file(path)?;
+        for _ in 0..args.count {
+            let password = generate()?;
+            writeln!(out, "{}", *password)?;
+            destroy_password(password);
+        }
+        out.sync_all()?;
+        // The new file's directory entry has to reach the disk too, or a crash can still lose it
+        #[cfg(unix)]
+        File::open(parent_dir(path))?.sync_all()?;
+        if !args.quiet {
+            let noun = if args.count == 1 {
+                "password"
+            } else {
+                "passwords"
+            };
+            eprintln!("{} {} written to {}.", args.count, noun, path.display());
+        }
+        return Ok(());
+    }
+
+    // Print in full when skipping the clipboard; one clipboard slot can't hold a batch anyway
+    if args.no_clipboard || args.count > 1 {
+        for _ in 0..args.count {
+            let password = generate()?;
+            println!("{}", *password);
+            destroy_password(password);
+        }
+        return Ok(());
+    }
+
+    // Copy and leave at once, printing nothing, for hotkeys; clipboard errors still fail loudly
+    if args.copy_only {
+        let password = generate()?;
+        let mut clipboard = Clipboard::new()?;
+        clipboard.copy(&password)?;
+        return Ok(());
+    }
+
     // Display the password
-    let password = generate_password(length, include_symbols);
+    let mut password = generate()?;
 
     println!(
-        "Generated password: {}{}{}",
-        &password[0..5],
-        "●".repeat(length - 7),
-        &password[length - 3..]
+        "Strength: {} ({:.0} bits)",
+        classify_strength(entropy),
+        entropy
     );
 
-    // Copy to clipboard
-    let mut clipboard = Clipboard::new().unwrap();
-    clipboard.set_text(password.clone()).unwrap();
+    // Copy to clipboard, falling back to stdout when there's no clipboard to copy to
+    info!("clipboard backend: {}", clipboard_backend());
+    let mut stdout = io::stdout();
+    let mut clipboard = match Clipboard::new() {
+        Ok(clipboard) => clipboard,
+        Err(e) => {
+            show_password(&mut stdout, &password, &args)?;
+            return print_instead(e, password);
+        }
+    };
+
+    // Ready before the copy, so no prompt below can be interrupted with the password left behind
+    if !args.no_auto_clear && timeout > 0 {
+        clear_on_interrupt();
+    }
+
+    // Hold the copy back until asked for; without a terminal to answer on, copy right away
+    let accept = args.copy_on_accept && io::stdin().is_terminal();
+    let mut input = io::stdin().lock();
+    let shown_width = match show_then_copy(
+        &mut clipboard,
+        &password,
+        &args,
+        accept,
+        &mut input,
+        &mut stdout,
+        &COPIED,
+    ) {
+        Ok(shown_width) => shown_width,
+        Err(AppError::Clipboard(e)) => return print_instead(e, password),
+        Err(e) => return Err(e),
+    };
+
+    // Offer fresh passwords until one is kept; without a terminal to answer on, keep the first
+    let shown_width = if args.interactive && io::stdin().is_terminal() {
+        offer_another(
+            &mut clipboard,
+            &mut password,
+            shown_width,
+            &args,
+            &mut input,
+            &mut stdout,
+            &mut generate,
+        )?
+    } else {
+        shown_width
+    };
+    drop(input);
+
+    // Warn even with --quiet, since nothing will clean up after us
+    if args.no_auto_clear {
+        eprintln!(
+            "Warning: the clipboard will NOT be cleared automatically. Clear it yourself once you're done."
+        );
+        return Ok(());
+    }
+
+    if timeout == 0 {
+        if !args.quiet {
+            println!("Password copied to clipboard.");
+        }
+        return Ok(());
+    }
+
+    info!("clearing the clipboard in {} seconds", timeout);
+    let countdown = Countdown {
+        seconds: timeout,
+        tick: Duration::from_secs(1),
+        progress: progress_bar(args.progress, &io::stdout()),
+    };
+    count_down(
+        &mut clipboard,
+        password,
+        &countdown,
+        shown_width,
+        &args,
+        &mut stdout,
+    )
+}
+
+/// The timer shown while the password waits on the clipboard.
+struct Countdown {
+    /// Seconds before the clipboard is cleared.
+    seconds: u64,
+    /// How long each of those seconds takes; a real second outside tests.
+    tick: Duration,
+    /// Draw a shrinking bar instead of the plain seconds left.
+    progress: bool,
+}
+
+/// Whether `--progress` gets its bar, which only a terminal can redraw in place.
+fn progress_bar(requested: bool, stdout: &impl IsTerminal) -> bool {
+    requested && stdout.is_terminal()
+}
+
+/// Shows the countdown, then clears the clipboard and hides the password line.
+///
+/// Stops early, leaving the clipboard alone, once something else is copied.
+fn count_down(
+    clipboard: &mut impl SecretClipboard,
+    password: Zeroizing<String>,
+    countdown: &Countdown,
+    shown_width: usize,
+    args: &OutputArgs,
+    out: &mut impl Write,
+) -> Result<(), AppError> {
+    let Countdown {
+        seconds: timeout,
+        tick,
+        progress,
+    } = *countdown;
+    let length = password.len();
+    if args.quiet {
+        for _ in 0..timeout {
+            sleep(tick);
+            if !still_on_clipboard(clipboard, &password) {
+                info!("something else was copied, leaving the clipboard alone");
+                return Ok(());
+            }
+        }
+        return clear_clipboard(clipboard, &password);
+    }
+
+    // Display the timer; the first, widest tick sets how far the line reaches
+    let timer = |i: u64| {
+        if progress {
+            // Every tick has the same width, so each one fully covers the last
+            let left = (i as usize * PROGRESS_WIDTH).div_ceil(timeout as usize);
+            format!(
+                "\r[{}{}] {:4}s left (Ctrl-C to clear now)",
+                "█".repeat(left),
+                "░".repeat(PROGRESS_WIDTH - left),
+                i
+            )
+        } else {
+            format!("\rSeconds remaining: {:2} (Ctrl-C to clear now)", i)
+        }
+    };
+    let timer_width = timer(timeout).chars().count() - 1;
+    for i in (1..=timeout).rev() {
+        write!(out, "{}", timer(i))?;
+        out.flush()?;
+        sleep(tick);
+
+        // Stop once something else is copied, rather than wiping it when the timer runs out
+        if !still_on_clipboard(clipboard, &password) {
+            info!("something else was copied, leaving the clipboard alone");
+            destroy_password(password);
+            if args.no_clear_screen {
+                writeln!(out, "\nThe clipboard changed, so it was left as is.")?;
+            } else {
+                erase_lines(out, shown_width, timer_width)?;
+                writeln!(
+                    out,
+                    "Password has been hidden. The clipboard changed, so it was left as is."
+                )?;
+            }
+            return Ok(());
+        }
+    }
+
+    // Clear the clipboard
+    clear_clipboard(clipboard, &password)?;
 
     let len = destroy_password(password);
 
     // #[cfg(debug_assertions)] // Only check in debug builds.
     assert_eq!(length, len);
 
-    // Display the timer
-    for i in (1..=15).rev() {
-        print!("\rSeconds remaining: {:2}", i);
-        io::stdout().flush().unwrap();
-        sleep(Duration::from_secs(1));
+    // Leave the masked line in the scrollback if asked, only moving past the timer
+    if args.no_clear_screen {
+        writeln!(out, "\nPassword removed from clipboard.")?;
+    } else {
+        erase_lines(out, shown_width, timer_width)?;
+        writeln!(out, "Password has been hidden and removed from clipboard.")?;
     }
+    Ok(())
+}
 
-    // Clear the clipboard
-    clipboard.clear().unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// What happened to the clipboard and the input, in order.
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    /// A clipboard kept in memory that notes every change in the log.
+    struct MockClipboard {
+        text: Option<String>,
+        log: Log,
+        /// Reads left before another app copies something else, if one does.
+        reads_until_replaced: Option<usize>,
+    }
+
+    impl SecretClipboard for MockClipboard {
+        fn copy(&mut self, password: &str) -> Result<(), arboard::Error> {
+            self.log.borrow_mut().push(format!("copy {}", password));
+            self.text = Some(password.to_string());
+            Ok(())
+        }
+
+        fn text(&mut self) -> Result<Zeroizing<String>, arboard::Error> {
+            if let Some(reads) = &mut self.reads_until_replaced {
+                if *reads == 0 {
+                    self.text = Some("copied elsewhere".to_string());
+                }
+                *reads = reads.saturating_sub(1);
+            }
+            self.text
+                .clone()
+                .map(Zeroizing::new)
+                .ok_or(arboard::Error::ContentNotAvailable)
+        }
+
+        fn clear(&mut self) -> Result<(), arboard::Error> {
+            self.log.borrow_mut().push("clear".to_string());
+            self.text = None;
+            Ok(())
+        }
+    }
+
+    /// Typed input, one line at a time, that notes each line in the log as it's read.
+    struct ScriptedInput {
+        lines: Vec<&'static str>,
+        pos: usize,
+        log: Log,
+    }
+
+    impl Read for ScriptedInput {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let read = self.fill_buf()?.read(buf)?;
+            self.consume(read);
+            Ok(read)
+        }
+    }
+
+    impl BufRead for ScriptedInput {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            Ok(match self.lines.first() {
+                Some(line) => &line.as_bytes()[self.pos..],
+                None => &[],
+            })
+        }
+
+        fn consume(&mut self, amount: usize) {
+            self.pos += amount;
+            if self
+                .lines
+                .first()
+                .is_some_and(|line| self.pos == line.len())
+            {
+                let line = self.lines.remove(0);
+                self.log.borrow_mut().push(format!("read {:?}", line));
+                self.pos = 0;
+            }
+        }
+    }
+
+    /// Terminal output that notes what's printed in the log, one entry per run of writes.
+    struct LoggedOutput {
+        log: Log,
+    }
+
+    impl Write for LoggedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let text = String::from_utf8_lossy(buf);
+            let mut log = self.log.borrow_mut();
+            match log.last_mut() {
+                Some(last) if last.starts_with("print ") => last.push_str(&text),
+                _ => log.push(format!("print {}", text)),
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn clipboard(log: &Log) -> MockClipboard {
+        MockClipboard {
+            text: None,
+            log: Rc::clone(log),
+            reads_until_replaced: None,
+        }
+    }
+
+    fn input(log: &Log, lines: &[&'static str]) -> ScriptedInput {
+        ScriptedInput {
+            lines: lines.to_vec(),
+            pos: 0,
+            log: Rc::clone(log),
+        }
+    }
 
-    // Move up one line
-    print!("\x1b[1A");
-    // Overwrite the password and timer with backspaces
-    print!("\r{}", " ".repeat(len + 100)); // Clear the password line
-    print!("\r{}", " ".repeat(50)); // Clear the timer line
-    print!("\r"); // Move the cursor to the beginning of the line
-    io::stdout().flush().unwrap();
+    fn output_args(flags: &[&str]) -> OutputArgs {
+        Cli::parse_from(["pwgen"].iter().chain(flags)).output
+    }
+
+    #[test]
+    fn interactive_regeneration_copies_each_new_password() {
+        let log = Log::default();
+        let mut clipboard = clipboard(&log);
+        let mut fresh = ["second-password", "third-password"].into_iter();
+        let mut generate = || Ok(Zeroizing::new(fresh.next().unwrap().to_string()));
+        let mut password = Zeroizing::new("first-password".to_string());
+        let mut out = Vec::new();
+
+        offer_another(
+            &mut clipboard,
+            &mut password,
+            0,
+            &output_args(&["--interactive"]),
+            &mut input(&log, &["r\n", "R\n", "\n"]),
+            &mut out,
+            &mut generate,
+        )
+        .unwrap();
+
+        assert_eq!(password.as_str(), "third-password");
+        assert_eq!(clipboard.text.as_deref(), Some("third-password"));
+        assert_eq!(
+            *log.borrow(),
+            [
+                "read \"r\\n\"",
+                "copy second-password",
+                "read \"R\\n\"",
+                "copy third-password",
+                "read \"\\n\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn interactive_keeps_the_password_when_input_ends() {
+        let log = Log::default();
+        let mut clipboard = clipboard(&log);
+        let mut generate =
+            || -> Result<Zeroizing<String>, AppError> { panic!("nothing should be regenerated") };
+        let mut password = Zeroizing::new("first-password".to_string());
+
+        offer_another(
+            &mut clipboard,
+            &mut password,
+            0,
+            &output_args(&["--interactive"]),
+            &mut input(&log, &[]),
+            &mut Vec::new(),
+            &mut generate,
+        )
+        .unwrap();
+
+        assert_eq!(password.as_str(), "first-password");
+        assert!(log.borrow().is_empty());
+    }
+
+    /// Runs a countdown with instant ticks and returns what it printed.
+    fn count_down_output(flags: &[&str], progress: bool, clipboard: &mut MockClipboard) -> String {
+        let countdown = Countdown {
+            seconds: 3,
+            tick: Duration::ZERO,
+            progress,
+        };
+        let password = Zeroizing::new("the-password".to_string());
+        clipboard.copy(&password).unwrap();
+        let mut out = Vec::new();
+        count_down(
+            clipboard,
+            password,
+            &countdown,
+            20,
+            &output_args(flags),
+            &mut out,
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn no_clear_screen_never_moves_the_cursor() {
+        let log = Log::default();
+        let shown = count_down_output(&["--no-clear-screen"], false, &mut clipboard(&log));
+        assert!(!shown.contains('\x1b'), "{:?}", shown);
+        assert!(shown.ends_with("\nPassword removed from clipboard.\n"));
+        assert_eq!(*log.borrow(), ["copy the-password", "clear"]);
+
+        // Without it, the password and timer lines are erased
+        let shown = count_down_output(&[], false, &mut clipboard(&log));
+        assert!(shown.contains("\x1b[1A"), "{:?}", shown);
+        assert!(shown.ends_with("Password has been hidden and removed from clipboard.\n"));
+    }
+
+    #[test]
+    fn progress_bar_needs_a_terminal() {
+        let file = File::open(env!("CARGO_MANIFEST_DIR")).unwrap();
+        assert!(!progress_bar(true, &file));
+        assert!(!progress_bar(false, &file));
+
+        // Plain text is what's printed when the bar is off
+        let log = Log::default();
+        let shown = count_down_output(&["--progress"], false, &mut clipboard(&log));
+        assert!(shown.contains("Seconds remaining:  3"), "{:?}", shown);
+        assert!(!shown.contains('█'));
+        let shown = count_down_output(&["--progress"], true, &mut clipboard(&log));
+        assert!(shown.contains('█'), "{:?}", shown);
+        assert!(!shown.contains("Seconds remaining"));
+    }
+
+    #[test]
+    fn template_tokens_each_get_their_own_password() {
+        let mut rng = SecretRng::new(Some([7; 32]));
+        let defaults = PasswordOptions::default();
+        let template = "user: {{pw:12}}\nadmin: {{pw:20}}\nleft: {{pw:16";
+        let filled = fill_tokens(template, &defaults, &mut rng).unwrap();
+        let lines: Vec<&str> = filled.lines().collect();
+        let user = lines[0].strip_prefix("user: ").unwrap();
+        let admin = lines[1].strip_prefix("admin: ").unwrap();
+        assert_eq!(user.chars().count(), 12);
+        assert_eq!(admin.chars().count(), 20);
+        assert!(!user.contains("{{") && !admin.contains("{{"));
+        assert_eq!(lines[2], "left: {{pw:16");
 
-    println!("Password has been hidden and removed from clipboard.");
+        let untouched = fill_tokens("no tokens {{pw}}", &defaults, &mut rng).unwrap();
+        assert_eq!(untouched.as_str(), "no tokens {{pw}}");
+        assert!(matches!(
+            fill_tokens("{{pw:abc}}", &defaults, &mut rng),
+            Err(AppError::Config(_))
+        ));
+    }
+
+    /// Set for the copy of the test binary that `panic_output_never_quotes_the_password` starts.
+    const PANIC_CHILD: &str = "PWGEN_TEST_PANIC_CHILD";
+
+    #[test]
+    fn panic_while_holding_the_password() {
+        if env::var_os(PANIC_CHILD).is_none() {
+            return;
+        }
+        install_panic_hook();
+        let password = "ä-hunter2-secret";
+        // Slicing inside a multi-byte character panics with the whole string in the message
+        let sliced = panic::catch_unwind(|| password[..1].len());
+        assert!(sliced.is_err());
+    }
+
+    #[test]
+    fn panic_output_never_quotes_the_password() {
+        // Re-run just the panicking test in its own process, so its hook can't affect this one
+        let output = process::Command::new(env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "tests::panic_while_holding_the_password",
+                "--nocapture",
+            ])
+            .env(PANIC_CHILD, "1")
+            .env("RUST_BACKTRACE", "0")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains("Error: pwgen crashed at "), "{}", stderr);
+        assert!(stderr.contains("The panic message is withheld"));
+        assert!(!stderr.contains("hunter2"), "{}", stderr);
+    }
+
+    #[test]
+    fn copy_on_accept_shows_then_waits_then_copies() {
+        let log = Log::default();
+        let copied = AtomicBool::new(false);
+        let shown_width = show_then_copy(
+            &mut clipboard(&log),
+            "the-password",
+            &output_args(&["--copy-on-accept", "--show"]),
+            true,
+            &mut input(&log, &["\n"]),
+            &mut LoggedOutput {
+                log: Rc::clone(&log),
+            },
+            &copied,
+        )
+        .unwrap();
+
+        assert_eq!(shown_width, "Generated password: the-password".len());
+        assert!(copied.load(Ordering::SeqCst));
+        assert_eq!(
+            *log.borrow(),
+            [
+                "print Generated password: the-password\nPress Enter to copy the password to the clipboard...",
+                "read \"\\n\"",
+                "print \x1b[1A\r\x1b[2K",
+                "copy the-password",
+            ]
+        );
+    }
+
+    #[test]
+    fn without_accept_the_password_is_copied_at_once() {
+        let log = Log::default();
+        let copied = AtomicBool::new(false);
+        show_then_copy(
+            &mut clipboard(&log),
+            "the-password",
+            &output_args(&[]),
+            false,
+            &mut input(&log, &["\n"]),
+            &mut LoggedOutput {
+                log: Rc::clone(&log),
+            },
+            &copied,
+        )
+        .unwrap();
+
+        assert!(copied.load(Ordering::SeqCst));
+        // Masked by default, and nothing read before copying
+        assert_eq!(
+            *log.borrow(),
+            [
+                "print Generated password: t●●●●●●●●●●d\n",
+                "copy the-password"
+            ]
+        );
+    }
+
+    #[test]
+    fn countdown_leaves_a_changed_clipboard_alone() {
+        for (flags, note) in [
+            (
+                &[][..],
+                "Password has been hidden. The clipboard changed, so it was left as is.\n",
+            ),
+            (
+                &["--no-clear-screen"][..],
+                "\nThe clipboard changed, so it was left as is.\n",
+            ),
+        ] {
+            let log = Log::default();
+            let mut clipboard = MockClipboard {
+                reads_until_replaced: Some(1),
+                ..clipboard(&log)
+            };
+            let shown = count_down_output(flags, false, &mut clipboard);
+            assert!(shown.ends_with(note), "{:?}", shown);
+            // Stopped on the second tick, the first that saw the other app's copy
+            assert!(!shown.contains("Seconds remaining:  1"), "{:?}", shown);
+            assert_eq!(clipboard.text.as_deref(), Some("copied elsewhere"));
+            assert_eq!(*log.borrow(), ["copy the-password"]);
+        }
+    }
 }
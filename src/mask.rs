@@ -0,0 +1,158 @@
+//! Masked display of a password, so it can be recognised without being shown.
+
+const MASK: &str = "●";
+// Anything shorter would reveal too much of the password, so mask it entirely
+const MIN_PARTIAL_LENGTH: usize = 8;
+// At most 1/5th (20%) of the password is ever revealed, counting both ends together
+const REVEALED_FRACTION: usize = 5;
+
+/// How many characters a masked password shows at each end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reveal {
+    pub leading: usize,
+    pub trailing: usize,
+}
+
+impl Reveal {
+    /// Shows `count` characters at both ends.
+    pub fn both(count: usize) -> Self {
+        Reveal {
+            leading: count,
+            trailing: count,
+        }
+    }
+}
+
+impl Default for Reveal {
+    fn default() -> Self {
+        Reveal {
+            leading: 5,
+            trailing: 3,
+        }
+    }
+}
+
+/// Masks the middle of `password`, revealing only the characters `reveal` asks for at each end.
+///
+/// Short passwords reveal fewer characters, so at most a fifth of the password is ever shown.
+pub fn mask_password(password: &str, reveal: Reveal) -> String {
+    // Work on characters rather than bytes so a multi-byte character is never split
+    let chars: Vec<char> = password.chars().collect();
+    let length = chars.len();
+    if length < MIN_PARTIAL_LENGTH {
+        return MASK.repeat(length);
+    }
+
+    // Split the budget evenly, then let either end use what the other leaves over
+    let budget = length / REVEALED_FRACTION;
+    let leading = reveal.leading.min(budget.div_ceil(2));
+    let trailing = reveal.trailing.min(budget - leading);
+    let leading = reveal.leading.min(budget - trailing);
+    let mut masked: String = chars[..leading].iter().collect();
+    masked.push_str(&MASK.repeat(length - leading - trailing));
+    masked.extend(&chars[length - trailing..]);
+    masked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// How many characters of `masked` are revealed rather than masked.
+    fn revealed(masked: &str) -> usize {
+        masked.chars().filter(|c| !MASK.contains(*c)).count()
+    }
+
+    #[test]
+    fn short_and_long_passwords_mask_without_panicking() {
+        assert_eq!(mask_password("a", Reveal::default()), "●");
+        assert_eq!(mask_password("abcdefg", Reveal::default()), "●●●●●●●");
+        assert_eq!(mask_password("abcdefgh", Reveal::default()), "a●●●●●●●");
+        let password = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWX";
+        let masked = mask_password(password, Reveal::default());
+        assert_eq!(masked, format!("abcde{}VWX", MASK.repeat(42)));
+        assert_eq!(masked.chars().count(), 50);
+        assert_eq!(revealed(&masked), 8);
+    }
+
+    #[test]
+    fn multi_byte_characters_are_never_split() {
+        let password = "äöüßéèêë€✓";
+        assert_eq!(mask_password(password, Reveal::default()), "ä●●●●●●●●✓");
+        let password = "🔑".repeat(20);
+        let masked = mask_password(&password, Reveal::both(2));
+        assert_eq!(masked, format!("🔑🔑{}🔑🔑", MASK.repeat(16)));
+    }
+
+    #[test]
+    fn reveal_count_is_clamped_per_length() {
+        let password = |length: usize| "x".repeat(length);
+        // Zero reveals nothing at any length
+        for length in [1, 8, 20, 100] {
+            let masked = mask_password(&password(length), Reveal::both(0));
+            assert_eq!(masked, MASK.repeat(length));
+        }
+        // (length, reveal at each end, revealed at the start, revealed at the end)
+        let cases = [
+            (12, 5, 1, 1),
+            (20, 1, 1, 1),
+            (20, 2, 2, 2),
+            (20, 10, 2, 2),
+            (50, 3, 3, 3),
+            (100, 10, 10, 10),
+            (100, 50, 10, 10),
+        ];
+        for (length, reveal, leading, trailing) in cases {
+            let masked = mask_password(&password(length), Reveal::both(reveal));
+            let expected = format!(
+                "{}{}{}",
+                "x".repeat(leading),
+                MASK.repeat(length - leading - trailing),
+                "x".repeat(trailing)
+            );
+            assert_eq!(masked, expected, "length {}, reveal {}", length, reveal);
+        }
+        // An end asking for nothing leaves its share to the other
+        let leading_only = Reveal {
+            leading: 5,
+            trailing: 0,
+        };
+        assert_eq!(revealed(&mask_password(&password(20), leading_only)), 4);
+    }
+
+    #[test]
+    fn never_reveals_more_than_a_fifth() {
+        for length in 1..=100 {
+            let password = "x".repeat(length);
+            for leading in 0..=length {
+                for trailing in [0, 1, 3, length / 2, length] {
+                    let masked = mask_password(&password, Reveal { leading, trailing });
+                    assert_eq!(masked.chars().count(), length);
+                    assert!(
+                        revealed(&masked) <= length / REVEALED_FRACTION,
+                        "length {}, reveal {}/{}",
+                        length,
+                        leading,
+                        trailing
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn short_masks_hide_most_of_the_password() {
+        // At most one character shows below ten, and at most two up to twelve
+        for (length, most) in [(8, 1), (9, 1), (10, 2), (11, 2), (12, 2)] {
+            let password: String = ('a'..).take(length).collect();
+            for reveal in [Reveal::default(), Reveal::both(5), Reveal::both(1)] {
+                let masked = mask_password(&password, reveal);
+                let shown = revealed(&masked);
+                assert!(shown <= most, "length {}: {}", length, masked);
+                // Whatever is shown sits at the ends, with the middle fully masked
+                let middle: String = masked.chars().skip(1).take(length - 2).collect();
+                assert_eq!(middle, MASK.repeat(length - 2), "length {}", length);
+            }
+        }
+    }
+}
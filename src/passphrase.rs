@@ -0,0 +1,296 @@
+//! Diceware-style passphrases drawn from the EFF large wordlist.
+
+use crate::{estimate_entropy, DIGITS, SYMBOLS};
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use rand::{CryptoRng, Rng};
+
+const WORDLIST: &str = include_str!("eff_large_wordlist.txt");
+
+/// What goes between the words of a passphrase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Separator {
+    /// The same character in every gap.
+    Fixed(char),
+    /// A random digit per gap, like `horse4battery9staple`.
+    RandomDigit,
+    /// A random symbol per gap, like `horse$battery]staple`.
+    RandomSymbol,
+}
+
+/// Which words of a passphrase start with a capital letter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Capitalization {
+    /// Every word stays lowercase.
+    #[default]
+    None,
+    /// Every word is capitalized, like `Correct-Horse-Battery`. Adds no entropy.
+    Each,
+    /// One randomly chosen word is capitalized, like `correct-Horse-battery`. Adds only log2 of the word count.
+    RandomWord,
+}
+
+impl Separator {
+    /// Characters a gap is drawn from.
+    fn chars(self) -> Vec<char> {
+        match self {
+            Separator::Fixed(c) => vec![c],
+            Separator::RandomDigit => DIGITS.chars().collect(),
+            Separator::RandomSymbol => SYMBOLS.chars().collect(),
+        }
+    }
+}
+
+/// Every word a passphrase may be built from.
+pub fn wordlist() -> Vec<&'static str> {
+    WORDLIST.lines().collect()
+}
+
+/// Generates a passphrase of `word_count` words joined by `separator`, drawing from the operating system's CSPRNG.
+pub fn generate_passphrase(
+    word_count: usize,
+    separator: Separator,
+    capitalization: Capitalization,
+) -> String {
+    generate_passphrase_with_rng(word_count, separator, capitalization, &mut OsRng)
+}
+
+/// Generates a passphrase of `word_count` words joined by `separator`, drawing from `rng`.
+pub fn generate_passphrase_with_rng<R: Rng + CryptoRng + ?Sized>(
+    word_count: usize,
+    separator: Separator,
+    capitalization: Capitalization,
+    rng: &mut R,
+) -> String {
+    let words = wordlist();
+    let gaps = separator.chars();
+    let capitalized = match capitalization {
+        Capitalization::RandomWord => Some(rng.gen_range(0..word_count.max(1))),
+        _ => None,
+    };
+
+    // Room for the longest words, so growing it never leaves a prefix of the secret in freed memory
+    let longest = longest_word(&words);
+    let gap_len = gaps.iter().map(|c| c.len_utf8()).max().unwrap_or(0);
+    let mut passphrase =
+        String::with_capacity(word_count * longest + word_count.saturating_sub(1) * gap_len);
+    for i in 0..word_count {
+        if i > 0 {
+            passphrase.push(*gaps.choose(rng).unwrap());
+        }
+        let word = words.choose(rng).unwrap();
+        if capitalization == Capitalization::Each || capitalized == Some(i) {
+            push_capitalized(&mut passphrase, word);
+        } else {
+            passphrase.push_str(word);
+        }
+    }
+    passphrase
+}
+
+/// Length in bytes of the longest of `words`.
+fn longest_word(words: &[&str]) -> usize {
+    words.iter().map(|word| word.len()).max().unwrap_or(0)
+}
+
+/// Appends `word` with its first letter in uppercase.
+fn push_capitalized(output: &mut String, word: &str) {
+    let mut chars = word.chars();
+    output.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+    output.extend(chars);
+}
+
+/// Bits of entropy in a passphrase of `word_count` words, including any random separators and capitalization.
+pub fn passphrase_entropy(
+    word_count: usize,
+    separator: Separator,
+    capitalization: Capitalization,
+) -> f64 {
+    let capitalized = match capitalization {
+        Capitalization::RandomWord => estimate_entropy(1, word_count),
+        _ => 0.0,
+    };
+    estimate_entropy(word_count, wordlist().len())
+        + estimate_entropy(word_count.saturating_sub(1), separator.chars().len())
+        + capitalized
+}
+
+/// Generates two capitalized words around a random digit and symbol, like `Horse7!Battery`, drawing from the operating system's CSPRNG.
+pub fn generate_hybrid() -> String {
+    generate_hybrid_with_rng(&mut OsRng)
+}
+
+/// Generates two capitalized words around a random digit and symbol, drawing from `rng`.
+pub fn generate_hybrid_with_rng<R: Rng + CryptoRng + ?Sized>(rng: &mut R) -> String {
+    let words = wordlist();
+    let digits = Separator::RandomDigit.chars();
+    let symbols = Separator::RandomSymbol.chars();
+
+    // Sized up front for the same reason as a passphrase
+    let mut hybrid = String::with_capacity(2 * longest_word(&words) + 2);
+    push_capitalized(&mut hybrid, words.choose(rng).unwrap());
+    hybrid.push(*digits.choose(rng).unwrap());
+    hybrid.push(*symbols.choose(rng).unwrap());
+    push_capitalized(&mut hybrid, words.choose(rng).unwrap());
+    hybrid
+}
+
+/// Bits of entropy in a hybrid password.
+///
+/// Only about 34: the capitals and the fixed layout add nothing, so it's memorable rather than strong.
+pub fn hybrid_entropy() -> f64 {
+    passphrase_entropy(2, Separator::RandomDigit, Capitalization::None)
+        + estimate_entropy(1, Separator::RandomSymbol.chars().len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashSet;
+
+    /// Every word in the list, lowercased, for looking up capitalized ones.
+    fn lowercase_words() -> HashSet<String> {
+        wordlist().iter().map(|word| word.to_lowercase()).collect()
+    }
+
+    /// Whether `passphrase` reads as `count` of `words` with a `gap` character between each.
+    ///
+    /// Backtracks, since a few words like `t-shirt` contain a hyphen themselves.
+    fn splits_into(
+        passphrase: &str,
+        count: usize,
+        words: &HashSet<String>,
+        gap: &dyn Fn(char) -> bool,
+    ) -> bool {
+        (1..=passphrase.len())
+            .filter(|end| passphrase.is_char_boundary(*end))
+            .any(|end| {
+                if !words.contains(&passphrase[..end].to_lowercase()) {
+                    return false;
+                }
+                let rest = &passphrase[end..];
+                match (count, rest.chars().next()) {
+                    (1, next) => next.is_none(),
+                    (_, Some(c)) => {
+                        gap(c) && splits_into(&rest[c.len_utf8()..], count - 1, words, gap)
+                    }
+                    (_, None) => false,
+                }
+            })
+    }
+
+    #[test]
+    fn each_separator_strategy_fills_every_gap() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let words = lowercase_words();
+        let strategies: [(Separator, &dyn Fn(char) -> bool); 3] = [
+            (Separator::Fixed(' '), &|c| c == ' '),
+            (Separator::RandomDigit, &|c| DIGITS.contains(c)),
+            (Separator::RandomSymbol, &|c| SYMBOLS.contains(c)),
+        ];
+        for (separator, gap) in strategies {
+            for _ in 0..50 {
+                let passphrase =
+                    generate_passphrase_with_rng(6, separator, Capitalization::None, &mut rng);
+                assert!(splits_into(&passphrase, 6, &words, gap), "{}", passphrase);
+            }
+        }
+    }
+
+    #[test]
+    fn random_separators_add_entropy_per_gap() {
+        let words = 6.0 * 7776f64.log2();
+        let fixed = passphrase_entropy(6, Separator::Fixed('-'), Capitalization::None);
+        let digit = passphrase_entropy(6, Separator::RandomDigit, Capitalization::None);
+        let symbol = passphrase_entropy(6, Separator::RandomSymbol, Capitalization::None);
+        assert!((fixed - words).abs() < 1e-9);
+        assert!((digit - (words + 5.0 * 10f64.log2())).abs() < 1e-9);
+        assert!((symbol - (words + 5.0 * 28f64.log2())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn capitalization_modes_pick_the_right_words() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let words = lowercase_words();
+        let capitalized = |passphrase: &str| {
+            passphrase
+                .split(' ')
+                .filter(|word| word.starts_with(|c: char| c.is_ascii_uppercase()))
+                .count()
+        };
+        for (capitalization, expected) in [
+            (Capitalization::None, 0),
+            (Capitalization::Each, 6),
+            (Capitalization::RandomWord, 1),
+        ] {
+            for _ in 0..50 {
+                let passphrase = generate_passphrase_with_rng(
+                    6,
+                    Separator::Fixed(' '),
+                    capitalization,
+                    &mut rng,
+                );
+                assert!(splits_into(&passphrase, 6, &words, &|c| c == ' '));
+                assert_eq!(capitalized(&passphrase), expected, "{}", passphrase);
+                // Only the first letter of a word is ever changed
+                assert!(passphrase
+                    .split(' ')
+                    .all(|word| !word[1..].contains(|c: char| c.is_ascii_uppercase())));
+            }
+        }
+    }
+
+    #[test]
+    fn capitalization_adds_little_entropy() {
+        let plain = passphrase_entropy(6, Separator::Fixed('-'), Capitalization::None);
+        let each = passphrase_entropy(6, Separator::Fixed('-'), Capitalization::Each);
+        let random = passphrase_entropy(6, Separator::Fixed('-'), Capitalization::RandomWord);
+        assert_eq!(each, plain);
+        assert!((random - (plain + 6f64.log2())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn hybrid_is_two_capitalized_words_around_a_digit_and_symbol() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let words: HashSet<&str> = wordlist().into_iter().collect();
+        let capitalized = |word: &str| {
+            word.starts_with(|c: char| c.is_ascii_uppercase())
+                && words.contains(word.to_lowercase().as_str())
+        };
+        for _ in 0..200 {
+            let hybrid = generate_hybrid_with_rng(&mut rng);
+            // No word holds a digit, so the only one marks where the middle starts
+            let digit = hybrid.find(|c: char| c.is_ascii_digit()).unwrap();
+            let (first, rest) = hybrid.split_at(digit);
+            let mut rest = rest.chars();
+            rest.next();
+            let symbol = rest.next().unwrap();
+            assert!(SYMBOLS.contains(symbol), "{}", hybrid);
+            assert!(capitalized(first), "{}", hybrid);
+            assert!(capitalized(rest.as_str()), "{}", hybrid);
+        }
+        let expected = 2.0 * 7776f64.log2() + 10f64.log2() + 28f64.log2();
+        assert!((hybrid_entropy() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn secrets_are_never_reallocated_while_built() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let longest = longest_word(&wordlist());
+        for _ in 0..50 {
+            let passphrase = generate_passphrase_with_rng(
+                6,
+                Separator::RandomSymbol,
+                Capitalization::RandomWord,
+                &mut rng,
+            );
+            assert_eq!(passphrase.capacity(), 6 * longest + 5);
+            assert_eq!(
+                generate_hybrid_with_rng(&mut rng).capacity(),
+                2 * longest + 2
+            );
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Passwords built from a template like `Aaa-999-!!`.
+
+use crate::{Error, DIGITS, LOWERCASE, SYMBOLS, UPPERCASE};
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use rand::{CryptoRng, Rng};
+
+/// One position of a pattern: a class to draw from, or a character to copy as is.
+enum Slot {
+    Class(&'static [u8]),
+    Literal(char),
+}
+
+/// Parses `pattern` into its slots.
+///
+/// `A`, `a`, `9` and `!` are placeholders and `\` escapes the next character. Every other letter
+/// and digit is reserved, so a typo like `Aa0` is caught instead of copied literally. A pattern
+/// without placeholders would always give the same password, so it's refused.
+fn slots(pattern: &str) -> Result<Vec<Slot>, Error> {
+    if pattern.is_empty() {
+        return Err(Error::ZeroLength);
+    }
+    let mut slots = Vec::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        let slot = match c {
+            'A' => Slot::Class(UPPERCASE.as_bytes()),
+            'a' => Slot::Class(LOWERCASE.as_bytes()),
+            '9' => Slot::Class(DIGITS.as_bytes()),
+            '!' => Slot::Class(SYMBOLS.as_bytes()),
+            // A trailing backslash has nothing to escape, so it stands for itself
+            '\\' => Slot::Literal(chars.next().unwrap_or('\\')),
+            c if c.is_ascii_alphanumeric() => {
+                return Err(Error::UnknownPlaceholder { placeholder: c })
+            }
+            c => Slot::Literal(c),
+        };
+        slots.push(slot);
+    }
+    if !slots.iter().any(|slot| matches!(slot, Slot::Class(_))) {
+        return Err(Error::NoPlaceholders);
+    }
+    Ok(slots)
+}
+
+/// Generates a password following `pattern`, drawing from the operating system's CSPRNG.
+pub fn generate_from_pattern(pattern: &str) -> Result<String, Error> {
+    generate_from_pattern_with_rng(pattern, &mut OsRng)
+}
+
+/// Generates a password following `pattern`, drawing from `rng`.
+pub fn generate_from_pattern_with_rng<R: Rng + CryptoRng + ?Sized>(
+    pattern: &str,
+    rng: &mut R,
+) -> Result<String, Error> {
+    Ok(slots(pattern)?
+        .into_iter()
+        .map(|slot| match slot {
+            Slot::Class(chars) => char::from(*chars.choose(rng).unwrap()),
+            Slot::Literal(c) => c,
+        })
+        .collect())
+}
+
+/// Bits of entropy in a password following `pattern`; literal characters add none.
+pub fn pattern_entropy(pattern: &str) -> Result<f64, Error> {
+    Ok(slots(pattern)?
+        .into_iter()
+        .map(|slot| match slot {
+            Slot::Class(chars) => (chars.len() as f64).log2(),
+            Slot::Literal(_) => 0.0,
+        })
+        .sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn empty_pattern_is_rejected() {
+        assert_eq!(generate_from_pattern(""), Err(Error::ZeroLength));
+        assert_eq!(pattern_entropy(""), Err(Error::ZeroLength));
+    }
+
+    #[test]
+    fn pattern_of_only_literals_is_rejected() {
+        for pattern in ["---", "\\A\\9", "\\"] {
+            assert_eq!(generate_from_pattern(pattern), Err(Error::NoPlaceholders));
+            assert_eq!(pattern_entropy(pattern), Err(Error::NoPlaceholders));
+        }
+    }
+
+    #[test]
+    fn placeholders_draw_from_their_class_and_literals_are_copied() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for _ in 0..100 {
+            let password = generate_from_pattern_with_rng("Aaa-999-!!", &mut rng).unwrap();
+            let chars: Vec<char> = password.chars().collect();
+            assert_eq!(chars.len(), 10);
+            assert!(chars[0].is_ascii_uppercase());
+            assert!(chars[1..3].iter().all(char::is_ascii_lowercase));
+            assert!(chars[4..7].iter().all(char::is_ascii_digit));
+            assert!(chars[8..].iter().all(|&c| SYMBOLS.contains(c)));
+            assert_eq!((chars[3], chars[7]), ('-', '-'));
+        }
+    }
+
+    #[test]
+    fn backslash_escapes_a_placeholder() {
+        let password = generate_from_pattern("\\A9").unwrap();
+        assert!(password.starts_with('A'));
+        assert!(password[1..].chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(password.len(), 2);
+    }
+
+    #[test]
+    fn unknown_placeholder_is_rejected() {
+        assert_eq!(
+            generate_from_pattern("Aa0"),
+            Err(Error::UnknownPlaceholder { placeholder: '0' })
+        );
+    }
+
+    #[test]
+    fn entropy_counts_only_placeholders() {
+        let expected = 3.0 * 26f64.log2() + 3.0 * 10f64.log2() + 2.0 * 28f64.log2();
+        assert!((pattern_entropy("Aaa-999-!!").unwrap() - expected).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,100 @@
+//! Pronounceable passwords built from alternating consonants and vowels.
+
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use rand::{CryptoRng, Rng};
+
+const CONSONANTS: &[char] = &[
+    'b', 'c', 'd', 'f', 'g', 'h', 'j', 'k', 'l', 'm', 'n', 'p', 'r', 's', 't', 'v', 'z',
+];
+const VOWELS: &[char] = &['a', 'e', 'i', 'o', 'u'];
+const SEPARATOR: char = '-';
+const WORD_LENGTH: usize = 5;
+
+/// Which kind of character goes in each position of a pronounceable password.
+fn layout(length: usize) -> Vec<Option<&'static [char]>> {
+    let mut slots = Vec::with_capacity(length);
+    let mut word_length = 0;
+    while slots.len() < length {
+        // Never end on a separator, just let the last word run one letter long
+        if word_length == WORD_LENGTH && slots.len() + 1 < length {
+            slots.push(None);
+            word_length = 0;
+        } else {
+            let letters = if word_length % 2 == 0 {
+                CONSONANTS
+            } else {
+                VOWELS
+            };
+            slots.push(Some(letters));
+            word_length += 1;
+        }
+    }
+    slots
+}
+
+/// Generates a pronounceable password of `length` characters like `tudan-pekov-sirto`, drawing from the operating system's CSPRNG.
+pub fn generate_pronounceable(length: usize) -> String {
+    generate_pronounceable_with_rng(length, &mut OsRng)
+}
+
+/// Generates a pronounceable password of `length` characters, drawing from `rng`.
+pub fn generate_pronounceable_with_rng<R: Rng + CryptoRng + ?Sized>(
+    length: usize,
+    rng: &mut R,
+) -> String {
+    layout(length)
+        .into_iter()
+        .map(|slot| match slot {
+            Some(letters) => *letters.choose(rng).unwrap(),
+            None => SEPARATOR,
+        })
+        .collect()
+}
+
+/// Bits of entropy in a pronounceable password of `length` characters.
+///
+/// Well below a random password of the same length, since each letter only comes from a handful of choices.
+pub fn pronounceable_entropy(length: usize) -> f64 {
+    layout(length)
+        .into_iter()
+        .flatten()
+        .map(|letters| (letters.len() as f64).log2())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn only_expected_letters_at_the_requested_length() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for length in [1, 5, 6, 10, 17, 50, 100] {
+            let password = generate_pronounceable_with_rng(length, &mut rng);
+            assert_eq!(password.chars().count(), length, "{}", password);
+            assert!(password
+                .chars()
+                .all(|c| CONSONANTS.contains(&c) || VOWELS.contains(&c) || c == SEPARATOR));
+            assert!(!password.ends_with(SEPARATOR));
+            for word in password.split(SEPARATOR) {
+                for (i, c) in word.chars().enumerate() {
+                    let letters = if i % 2 == 0 { CONSONANTS } else { VOWELS };
+                    assert!(letters.contains(&c), "{}", password);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn entropy_counts_only_the_letters() {
+        // One five-letter word: three consonants and two vowels
+        let word = 3.0 * 17f64.log2() + 2.0 * 5f64.log2();
+        assert!((pronounceable_entropy(5) - word).abs() < 1e-9);
+        // The separator adds nothing
+        assert!((pronounceable_entropy(11) - 2.0 * word).abs() < 1e-9);
+        assert!(pronounceable_entropy(20) < crate::estimate_entropy(20, 26));
+    }
+}
@@ -0,0 +1,123 @@
+//! The random number generator every secret is drawn from.
+
+use rand::rngs::{OsRng, StdRng};
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+/// The operating system's CSPRNG, a seeded `StdRng` for reproducible output, or a generator keyed by `derive`.
+pub enum SecretRng {
+    Os(OsRng),
+    Seeded(Box<StdRng>),
+    // StdRng may switch algorithms between rand releases, which would change every derived password
+    Derived(Box<ChaCha20Rng>),
+}
+
+impl SecretRng {
+    /// A seeded generator when `seed` is given, otherwise the operating system's CSPRNG.
+    pub fn new(seed: Option<[u8; 32]>) -> Self {
+        match seed {
+            Some(seed) => SecretRng::Seeded(Box::new(StdRng::from_seed(seed))),
+            None => SecretRng::Os(OsRng),
+        }
+    }
+
+    /// A generator seeded from a key derived from the master password.
+    pub fn derived(key: &[u8; 32]) -> Self {
+        SecretRng::Derived(Box::new(ChaCha20Rng::from_seed(*key)))
+    }
+}
+
+impl RngCore for SecretRng {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            SecretRng::Os(rng) => rng.next_u32(),
+            SecretRng::Seeded(rng) => rng.next_u32(),
+            SecretRng::Derived(rng) => rng.next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            SecretRng::Os(rng) => rng.next_u64(),
+            SecretRng::Seeded(rng) => rng.next_u64(),
+            SecretRng::Derived(rng) => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            SecretRng::Os(rng) => rng.fill_bytes(dest),
+            SecretRng::Seeded(rng) => rng.fill_bytes(dest),
+            SecretRng::Derived(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        match self {
+            SecretRng::Os(rng) => rng.try_fill_bytes(dest),
+            SecretRng::Seeded(rng) => rng.try_fill_bytes(dest),
+            SecretRng::Derived(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
+
+// Every variant is cryptographically secure; a seeded one is only predictable to whoever knows the seed
+impl CryptoRng for SecretRng {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pwgen::{generate_password_with_rng, PasswordOptions};
+    use std::collections::HashSet;
+
+    #[test]
+    fn same_seed_gives_the_same_passwords() {
+        let options = PasswordOptions::default();
+        let draw = |seed| {
+            let mut rng = SecretRng::new(Some(seed));
+            (0..5)
+                .map(|_| generate_password_with_rng(&options, &mut rng).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draw([7; 32]), draw([7; 32]));
+        assert_ne!(draw([7; 32]), draw([8; 32]));
+    }
+
+    #[test]
+    fn every_source_gives_valid_passwords() {
+        let options = PasswordOptions {
+            length: 24,
+            require_classes: true,
+            ..PasswordOptions::default()
+        };
+        let pool = options.pool();
+        let sources = [
+            SecretRng::new(None),
+            SecretRng::new(Some([7; 32])),
+            SecretRng::derived(&[7; 32]),
+        ];
+        for mut rng in sources {
+            let passwords: Vec<String> = (0..20)
+                .map(|_| generate_password_with_rng(&options, &mut rng).unwrap())
+                .collect();
+            for password in &passwords {
+                assert_eq!(password.chars().count(), 24);
+                assert!(password.chars().all(|c| pool.contains(&c)), "{}", password);
+            }
+            assert_eq!(passwords.iter().collect::<HashSet<_>>().len(), 20);
+        }
+    }
+
+    #[test]
+    fn same_key_gives_the_same_derived_passwords() {
+        let options = PasswordOptions::default();
+        let draw = |key| {
+            let mut rng = SecretRng::derived(&key);
+            (0..5)
+                .map(|_| generate_password_with_rng(&options, &mut rng).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draw([7; 32]), draw([7; 32]));
+        assert_ne!(draw([7; 32]), draw([8; 32]));
+    }
+}
@@ -0,0 +1,130 @@
+//! The `--stats` diagnostic: how evenly, and how steadily, passwords are drawn from the pool.
+
+use crate::error::AppError;
+use crate::rng::SecretRng;
+use pwgen::{Generator, PasswordOptions};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+/// How often each pool character came up across the samples, and how long each password took.
+///
+/// Only counts and durations are kept, never the passwords themselves.
+struct Tally {
+    counts: HashMap<char, usize>,
+    timings: Vec<Duration>,
+}
+
+/// Generates `samples` passwords and tallies them; every pool character has a count, even if it's 0.
+fn tally(
+    options: &PasswordOptions,
+    samples: usize,
+    rng: &mut SecretRng,
+) -> Result<Tally, AppError> {
+    let generator = Generator::new(options)?;
+    let mut counts: HashMap<char, usize> = options.pool().iter().map(|c| (*c, 0)).collect();
+    let mut timings = Vec::with_capacity(samples);
+    for _ in 0..samples {
+        let started = Instant::now();
+        let password = Zeroizing::new(generator.next_password_with_rng(rng)?);
+        timings.push(started.elapsed());
+        for c in password.chars() {
+            *counts.entry(c).or_insert(0) += 1;
+        }
+    }
+    Ok(Tally { counts, timings })
+}
+
+/// Generates `samples` passwords and prints how often each pool character came up, plus timings.
+pub fn print(
+    options: &PasswordOptions,
+    samples: usize,
+    rng: &mut SecretRng,
+) -> Result<(), AppError> {
+    let Tally { counts, timings } = tally(options, samples, rng)?;
+    let pool = options.pool();
+    let total: usize = counts.values().sum();
+    let expected = total as f64 / pool.len() as f64;
+    println!(
+        "Generated {} passwords of {} characters ({} characters in total).",
+        samples, options.length, total
+    );
+    println!(
+        "Pool: {} characters, each expected {:.1} times.",
+        pool.len(),
+        expected
+    );
+
+    // Pool order breaks ties, so the output is stable
+    let by_count = |pick: fn(usize, usize) -> bool| {
+        pool.iter()
+            .copied()
+            .reduce(|best, c| {
+                if pick(counts[&c], counts[&best]) {
+                    c
+                } else {
+                    best
+                }
+            })
+            .unwrap()
+    };
+    let (rarest, commonest) = (by_count(|a, b| a < b), by_count(|a, b| a > b));
+    println!(
+        "Least frequent: '{}' {} times, most frequent: '{}' {} times.",
+        rarest, counts[&rarest], commonest, counts[&commonest]
+    );
+
+    // A uniform pool gives a chi-square close to its degrees of freedom, give or take sqrt(2 * df)
+    let chi_square: f64 = counts
+        .values()
+        .map(|count| (*count as f64 - expected).powi(2) / expected)
+        .sum();
+    let degrees = (pool.len() - 1) as f64;
+    let spread = (2.0 * degrees).sqrt();
+    println!(
+        "Chi-square: {:.1} with {} degrees of freedom (about {} ± {:.1} if uniform).",
+        chi_square, degrees, degrees, spread
+    );
+    if chi_square > degrees + 3.0 * spread {
+        println!(
+            "The counts look biased. Class minimums, weights and rejection checks skew them on purpose."
+        );
+    } else {
+        println!("The counts are consistent with uniform sampling.");
+    }
+
+    let mean = timings.iter().sum::<Duration>() / samples as u32;
+    let fastest = timings.iter().min().unwrap();
+    let slowest = timings.iter().max().unwrap();
+    println!(
+        "Time per password: {:?} on average, {:?} fastest, {:?} slowest.",
+        mean, fastest, slowest
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tally_counts_every_character_drawn() {
+        let mut rng = SecretRng::new(Some([7; 32]));
+        for (length, symbols, samples) in [(12, true, 100), (30, false, 7), (10, true, 1)] {
+            let options = PasswordOptions {
+                length,
+                include_symbols: symbols,
+                ..PasswordOptions::default()
+            };
+            let Tally { counts, timings } = tally(&options, samples, &mut rng).unwrap();
+            assert_eq!(counts.values().sum::<usize>(), samples * length);
+            assert_eq!(timings.len(), samples);
+            // Nothing outside the pool is counted, and nothing in it is missing
+            let mut keys: Vec<char> = counts.keys().copied().collect();
+            let mut pool = options.pool();
+            keys.sort_unstable();
+            pool.sort_unstable();
+            assert_eq!(keys, pool);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Human-readable strength labels for entropy estimates.
+
+use crate::{estimate_entropy, DIGITS, LOWERCASE, SYMBOLS, UPPERCASE};
+use std::fmt;
+
+// Lower bounds, in bits, of each label above `Weak`
+const FAIR_BITS: f64 = 50.0;
+const STRONG_BITS: f64 = 80.0;
+const EXCELLENT_BITS: f64 = 120.0;
+
+/// How hard a password is to guess, judged by its entropy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Strength {
+    Weak,
+    Fair,
+    Strong,
+    Excellent,
+}
+
+impl fmt::Display for Strength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Strength::Weak => "Weak",
+            Strength::Fair => "Fair",
+            Strength::Strong => "Strong",
+            Strength::Excellent => "Excellent",
+        };
+        f.write_str(label)
+    }
+}
+
+/// Labels a password with `bits` of entropy.
+pub fn classify_strength(bits: f64) -> Strength {
+    if bits >= EXCELLENT_BITS {
+        Strength::Excellent
+    } else if bits >= STRONG_BITS {
+        Strength::Strong
+    } else if bits >= FAIR_BITS {
+        Strength::Fair
+    } else {
+        Strength::Weak
+    }
+}
+
+/// Bits of entropy in an existing password, judged by its length and the character classes it uses.
+///
+/// This is an upper bound: it assumes every character was picked at random, so a dictionary word
+/// scores far higher than it deserves.
+pub fn password_entropy(password: &str) -> f64 {
+    let classes = [LOWERCASE, UPPERCASE, DIGITS, SYMBOLS];
+    let mut pool: usize = classes
+        .iter()
+        .filter(|class| password.chars().any(|c| class.contains(c)))
+        .map(|class| class.len())
+        .sum();
+    // Characters outside the built-in classes only count once each
+    let mut others: Vec<char> = password
+        .chars()
+        .filter(|c| !classes.iter().any(|class| class.contains(*c)))
+        .collect();
+    others.sort_unstable();
+    others.dedup();
+    pool += others.len();
+    if pool == 0 {
+        return 0.0;
+    }
+    estimate_entropy(password.chars().count(), pool)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_change_exactly_at_the_boundaries() {
+        let cases = [
+            (0.0, Strength::Weak),
+            (49.99, Strength::Weak),
+            (50.0, Strength::Fair),
+            (79.99, Strength::Fair),
+            (80.0, Strength::Strong),
+            (119.99, Strength::Strong),
+            (120.0, Strength::Excellent),
+            (1000.0, Strength::Excellent),
+        ];
+        for (bits, strength) in cases {
+            assert_eq!(classify_strength(bits), strength, "{} bits", bits);
+        }
+        assert_eq!(Strength::Strong.to_string(), "Strong");
+    }
+
+    #[test]
+    fn existing_passwords_are_judged_by_the_classes_they_use() {
+        assert_eq!(password_entropy(""), 0.0);
+        assert!((password_entropy("abcdefghij") - estimate_entropy(10, 26)).abs() < 1e-9);
+        assert!((password_entropy("aB3!aB3!aB") - estimate_entropy(10, 90)).abs() < 1e-9);
+        // Characters outside the classes count once each
+        assert!((password_entropy("ééé") - estimate_entropy(3, 1)).abs() < 1e-9);
+    }
+}
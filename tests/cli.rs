@@ -0,0 +1,457 @@
+//! End-to-end checks that run the `pwgen` binary.
+
+use std::collections::HashSet;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{self, Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A `pwgen` command that can't see the user's config file, `PWGEN_*` variables or clipboard.
+fn pwgen() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_pwgen"));
+    command
+        .env("XDG_CONFIG_HOME", scratch("no-config"))
+        .env_remove("PWGEN_LENGTH")
+        .env_remove("PWGEN_SYMBOLS")
+        .env_remove("PWGEN_TIMEOUT")
+        .env_remove("DISPLAY")
+        .env_remove("WAYLAND_DISPLAY")
+        .stdin(Stdio::null());
+    command
+}
+
+/// A path in the temp directory unique to this test run, with nothing at it yet.
+fn scratch(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("pwgen-test-{}-{}", process::id(), name));
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_dir_all(&path);
+    path
+}
+
+#[test]
+fn fd_appends_to_an_inherited_descriptor() {
+    let path = scratch("fd-append.txt");
+    fs::write(&path, "first\n").unwrap();
+    let file = OpenOptions::new().append(true).open(&path).unwrap();
+
+    let output = pwgen()
+        .args(["--fd", "1", "-l", "12"])
+        .stdout(file)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let contents = fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0], "first");
+    assert_eq!(lines[1].chars().count(), 12);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn fd_that_isnt_open_is_rejected() {
+    let output = pwgen().args(["--fd", "97"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("file descriptor 97 isn't open"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn fd_open_only_for_reading_is_rejected() {
+    let path = scratch("fd-read-only.txt");
+    fs::write(&path, "contents\n").unwrap();
+    let output = pwgen()
+        .args(["--fd", "0"])
+        .stdin(fs::File::open(&path).unwrap())
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("file descriptor 0 isn't open for writing"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn random_length_is_reproducible_when_seeded() {
+    let run = || {
+        let output = pwgen()
+            .args([
+                "--rng-source",
+                "seeded",
+                "--seed",
+                "5eed",
+                "--length",
+                "random",
+            ])
+            .arg("--no-clipboard")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let password = run();
+    assert_eq!(password, run());
+    assert!((24..=48).contains(&password.trim_end().chars().count()));
+}
+
+#[test]
+fn output_file_holds_every_password_once() {
+    let path = scratch("output-file.txt");
+    let output = pwgen()
+        .args(["-n", "100", "-l", "16", "--output-file"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let contents = fs::read_to_string(&path).unwrap();
+    let passwords: Vec<&str> = contents.lines().collect();
+    assert_eq!(passwords.len(), 100);
+    assert!(passwords
+        .iter()
+        .all(|password| password.chars().count() == 16));
+    let unique: HashSet<&str> = passwords.iter().copied().collect();
+    assert_eq!(unique.len(), 100);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    // An existing file is never replaced
+    let again = pwgen().arg("--output-file").arg(&path).output().unwrap();
+    assert_eq!(again.status.code(), Some(2));
+    assert_eq!(fs::read_to_string(&path).unwrap(), contents);
+    fs::remove_file(&path).unwrap();
+}
+
+/// Runs `command` with `input` on stdin, killing it if it hasn't exited within five seconds.
+fn run_with_input(command: &mut Command, input: &str) -> Output {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while child.try_wait().unwrap().is_none() {
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("pwgen was still waiting for input after stdin closed");
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn ask_fails_cleanly_when_stdin_is_empty() {
+    let output = run_with_input(pwgen().arg("--ask"), "");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("input ended"), "{}", stderr);
+
+    // Input running out after the length is answered fails the same way
+    let output = run_with_input(pwgen().arg("--ask"), "12\n");
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn serve_answers_every_request_line() {
+    let requests = "12\n20 no-symbols\n5\nbogus\n\n16 symbols\n";
+    let output = run_with_input(pwgen().arg("--serve"), requests);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 6, "{}", stdout);
+    assert_eq!(lines[0].chars().count(), 12);
+    assert_eq!(lines[1].chars().count(), 20);
+    assert!(lines[1].chars().all(|c| c.is_ascii_alphanumeric()));
+    assert!(lines[2].starts_with("error: "), "{}", lines[2]);
+    assert_eq!(lines[3], "error: unexpected field 'bogus'");
+    assert_eq!(lines[4].chars().count(), 50);
+    assert_eq!(lines[5].chars().count(), 16);
+}
+
+/// The JSON objects `pwgen ARGS --format json` prints, one per line.
+fn json_lines(args: &[&str]) -> Vec<serde_json::Value> {
+    let output = pwgen()
+        .args(args)
+        .args(["--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn append_digits_adds_a_numeric_suffix() {
+    let plain = &json_lines(&["-l", "12"])[0];
+    for entry in json_lines(&["-l", "12", "--append-digits", "4", "-n", "20"]) {
+        let password = entry["password"].as_str().unwrap();
+        assert_eq!(password.chars().count(), 16);
+        assert_eq!(entry["length"], 16);
+        assert!(
+            password[12..].chars().all(|c| c.is_ascii_digit()),
+            "{}",
+            password
+        );
+        let added =
+            entry["entropy_bits"].as_f64().unwrap() - plain["entropy_bits"].as_f64().unwrap();
+        assert!((added - 4.0 * 10f64.log2()).abs() < 1e-9);
+    }
+
+    let entry = &json_lines(&["passphrase", "--words", "3", "--append-digits", "2"])[0];
+    let passphrase = entry["password"].as_str().unwrap();
+    assert!(passphrase[passphrase.len() - 2..]
+        .chars()
+        .all(|c| c.is_ascii_digit()));
+}
+
+#[test]
+fn json_batch_is_one_array_of_every_password() {
+    for count in ["1", "3", "25"] {
+        let output = pwgen()
+            .args(["-l", "16", "-n", count, "--format", "json-batch"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let batch: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let entries = batch.as_array().unwrap();
+        assert_eq!(entries.len(), count.parse::<usize>().unwrap());
+        for entry in entries {
+            assert_eq!(entry["password"].as_str().unwrap().chars().count(), 16);
+            assert_eq!(entry["length"], 16);
+            assert!(entry["entropy_bits"].as_f64().unwrap() > 0.0);
+            assert_eq!(entry["symbols"], true);
+        }
+    }
+}
+
+#[test]
+fn check_rates_a_password_given_as_an_argument() {
+    for (password, expected) in [
+        ("abc", "Strength: Weak (14 bits)"),
+        (
+            "correct horse battery staple",
+            "Strength: Excellent (133 bits)",
+        ),
+    ] {
+        let output = pwgen().args(["--check", password]).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        assert_eq!(String::from_utf8(output.stdout).unwrap().trim(), expected);
+    }
+}
+
+/// The single JSON object `command ARGS --format json` prints.
+fn json_entry(command: &mut Command, args: &[&str]) -> serde_json::Value {
+    let output = command
+        .args(args)
+        .args(["--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn environment_sets_defaults_that_flags_override() {
+    let entry = json_entry(
+        pwgen().env("PWGEN_LENGTH", "24").env("PWGEN_SYMBOLS", "no"),
+        &[],
+    );
+    assert_eq!(
+        (entry["length"].as_u64(), entry["symbols"].as_bool()),
+        (Some(24), Some(false))
+    );
+
+    let entry = json_entry(
+        pwgen()
+            .env("PWGEN_LENGTH", " 24 ")
+            .env("PWGEN_SYMBOLS", "FALSE"),
+        &["-l", "30", "--symbols"],
+    );
+    assert_eq!(
+        (entry["length"].as_u64(), entry["symbols"].as_bool()),
+        (Some(30), Some(true))
+    );
+}
+
+#[test]
+fn invalid_environment_values_are_rejected() {
+    let cases = [
+        (
+            "PWGEN_LENGTH",
+            "abc",
+            "invalid PWGEN_LENGTH 'abc': expected a length between 10 and 100",
+        ),
+        ("PWGEN_LENGTH", "5", "invalid PWGEN_LENGTH '5'"),
+        (
+            "PWGEN_SYMBOLS",
+            "maybe",
+            "invalid PWGEN_SYMBOLS 'maybe': expected true or false",
+        ),
+        ("PWGEN_TIMEOUT", "-1", "invalid PWGEN_TIMEOUT '-1'"),
+    ];
+    for (name, value, message) in cases {
+        let output = pwgen().env(name, value).output().unwrap();
+        assert_eq!(output.status.code(), Some(2));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(message), "{}", stderr);
+    }
+}
+
+#[test]
+fn completions_and_manpage_print_without_panicking() {
+    for shell in ["bash", "elvish", "fish", "powershell", "zsh"] {
+        let output = pwgen().args(["completions", shell]).output().unwrap();
+        assert!(output.status.success(), "{}: {:?}", shell, output);
+        assert!(output.stderr.is_empty(), "{}: {:?}", shell, output);
+        let script = String::from_utf8(output.stdout).unwrap();
+        assert!(script.contains("pwgen"), "{}", shell);
+    }
+
+    let output = pwgen().arg("manpage").output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let page = String::from_utf8(output.stdout).unwrap();
+    assert!(page.contains(".TH pwgen 1"), "{}", page);
+}
+
+#[test]
+fn version_info_prints_every_key() {
+    let output = pwgen().arg("--version-info").output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let keys: Vec<&str> = stdout
+        .lines()
+        .map(|line| line.split_once('=').unwrap().0)
+        .collect();
+    let expected = [
+        "version",
+        "commit",
+        "rustc",
+        "target",
+        "build_time",
+        "features",
+        "clipboard",
+    ];
+    assert_eq!(keys, expected, "{}", stdout);
+    assert!(stdout.starts_with(&format!("version={}\n", env!("CARGO_PKG_VERSION"))));
+}
+
+/// A `pwgen` command reading `contents` as its config file.
+fn pwgen_with_config(name: &str, contents: &str) -> Command {
+    let home = scratch(name);
+    fs::create_dir_all(home.join("pwgen")).unwrap();
+    fs::write(home.join("pwgen").join("config.toml"), contents).unwrap();
+    let mut command = pwgen();
+    command.env("XDG_CONFIG_HOME", home);
+    command
+}
+
+const PROFILES: &str = "
+length = 20
+symbols = false
+
+[profiles.banking]
+length = 40
+
+[profiles.wifi]
+symbols = true
+";
+
+#[test]
+fn settings_apply_in_the_documented_order() {
+    let length = |command: &mut Command, args: &[&str]| json_entry(command, args)["length"].clone();
+    let config = || pwgen_with_config("precedence", PROFILES);
+    // Config file < environment < profile < flags
+    assert_eq!(length(&mut config(), &[]), 20);
+    assert_eq!(length(config().env("PWGEN_LENGTH", "30"), &[]), 30);
+    assert_eq!(
+        length(
+            config().env("PWGEN_LENGTH", "30"),
+            &["--profile", "banking"]
+        ),
+        40
+    );
+    assert_eq!(
+        length(
+            config().env("PWGEN_LENGTH", "30"),
+            &["--profile", "banking", "-l", "50"]
+        ),
+        50
+    );
+    // A profile leaves the keys it doesn't set to the levels below it
+    let entry = json_entry(&mut config(), &["--profile", "wifi"]);
+    assert_eq!(
+        (entry["length"].as_u64(), entry["symbols"].as_bool()),
+        (Some(20), Some(true))
+    );
+    let entry = json_entry(&mut config(), &["--profile", "banking"]);
+    assert_eq!(entry["symbols"], false);
+}
+
+#[test]
+fn unknown_profile_lists_the_known_ones() {
+    let output = pwgen_with_config("unknown-profile", PROFILES)
+        .args(["--profile", "work"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("no profile 'work', expected one of: banking, wifi"),
+        "{}",
+        stderr
+    );
+
+    let output = pwgen().args(["--profile", "work"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("the config file defines none"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn audit_log_never_holds_the_password_or_flag_values() {
+    let path = scratch("audit.log");
+    let args = ["--rng-source", "seeded", "--seed", "abc", "-l20", "-n", "3"];
+    let entries = json_lines(&[&args[..], &["--audit-log", path.to_str().unwrap()]].concat());
+    let log = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(log.lines().count(), 3);
+    for entry in entries {
+        assert!(
+            !log.contains(entry["password"].as_str().unwrap()),
+            "{}",
+            log
+        );
+    }
+    assert!(!log.contains("abc"), "{}", log);
+    assert!(log.contains(r#""flags":["--rng-source","--seed","-l","-n","--audit-log","--format"]"#));
+}